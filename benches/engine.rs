@@ -0,0 +1,227 @@
+//! Throughput of the compression engine, measured on whole jobs from file to
+//! file, the way the TUI and the CLI run them. The inputs are generated from
+//! fixed seeds, so runs on the same machine compare like for like:
+//!
+//! cargo bench --bench engine -- --save-baseline before
+//! (change something)
+//! cargo bench --bench engine -- --baseline before
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use freya::{
+    Algorithm, CompressMessage, CompressionLevel, JobOptions, MAPPED_MIN_SIZE, ReadMode,
+    start_compression, start_decompression,
+};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
+
+const SIZES: [usize; 2] = [256 * 1024, 4 * 1024 * 1024];
+
+#[derive(Debug, Clone, Copy)]
+enum Data {
+    /// Words from a small vocabulary: compresses well, like logs and source code.
+    Text,
+    /// Fixed-size records with counters and small values, like a table dump.
+    Binary,
+    /// Incompressible, like media or already compressed files.
+    Random,
+}
+
+impl Data {
+    const ALL: [Data; 3] = [Data::Text, Data::Binary, Data::Random];
+
+    fn name(self) -> &'static str {
+        match self {
+            Data::Text => "text",
+            Data::Binary => "binary",
+            Data::Random => "random",
+        }
+    }
+
+    fn generate(self, len: usize) -> Vec<u8> {
+        const WORDS: [&str; 16] = [
+            "freya", "compress", "the", "file", "level", "frame", "of", "and", "zstd", "window",
+            "fast", "best", "to", "a", "ratio", "bytes",
+        ];
+        let mut rng = XorShift(0x9E37_79B9_7F4A_7C15);
+        let mut data = Vec::with_capacity(len + 16);
+        let mut record = 0u32;
+        while data.len() < len {
+            match self {
+                Data::Text => {
+                    let word = rng.next();
+                    data.extend(WORDS[(word % 16) as usize].as_bytes());
+                    data.push(if word.is_multiple_of(11) { b'\n' } else { b' ' });
+                }
+                Data::Binary => {
+                    data.extend(record.to_le_bytes());
+                    data.extend(((record * 37) % 1000).to_le_bytes()[..2].iter());
+                    data.extend((rng.next() % 64).to_le_bytes()[..2].iter());
+                    record += 1;
+                }
+                Data::Random => data.extend(rng.next().to_le_bytes()),
+            }
+        }
+        data.truncate(len);
+        data
+    }
+}
+
+struct XorShift(u64);
+
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
+
+// Waits for the job to end, failing the benchmark if it didn't succeed.
+fn finish(rx: mpsc::Receiver<CompressMessage>) {
+    for msg in rx {
+        match msg {
+            CompressMessage::Finished { .. } => return,
+            CompressMessage::Error(e) => panic!("job failed: {}", e),
+            _ => {}
+        }
+    }
+    panic!("job ended without Finished");
+}
+
+fn compress(input: &Path, output: &Path, level: CompressionLevel, options: JobOptions) {
+    let (tx, rx) = mpsc::channel();
+    start_compression(path(input), path(output), tx, level, options);
+    finish(rx);
+}
+
+fn decompress(input: &Path, output: &Path, options: JobOptions) {
+    let (tx, rx) = mpsc::channel();
+    start_decompression(path(input), path(output), tx, options);
+    finish(rx);
+}
+
+fn path(path: &Path) -> String {
+    path.to_string_lossy().to_string()
+}
+
+struct Scratch(PathBuf);
+
+impl Scratch {
+    fn new() -> Self {
+        let dir = std::env::temp_dir().join(format!("freya_bench_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        Self(dir)
+    }
+
+    fn file(&self, name: &str) -> PathBuf {
+        self.0.join(name)
+    }
+}
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        std::fs::remove_dir_all(&self.0).ok();
+    }
+}
+
+fn engine(c: &mut Criterion) {
+    let scratch = Scratch::new();
+    let options = JobOptions::default();
+
+    for data in Data::ALL {
+        for size in SIZES {
+            let input = scratch.file(&format!("{}-{}", data.name(), size));
+            std::fs::write(&input, data.generate(size)).unwrap();
+            let parameter = format!("{}/{}KiB", data.name(), size / 1024);
+
+            let mut group = c.benchmark_group("compress");
+            group.throughput(Throughput::Bytes(size as u64));
+            for level in CompressionLevel::ALL {
+                let output = scratch.file("out.zst");
+                group.bench_with_input(
+                    BenchmarkId::new(level.label(), &parameter),
+                    &input,
+                    |b, input| b.iter(|| compress(input, &output, level, options)),
+                );
+            }
+            group.finish();
+
+            // Throughput counts the decompressed bytes, so levels compare directly
+            let mut group = c.benchmark_group("decompress");
+            group.throughput(Throughput::Bytes(size as u64));
+            for level in CompressionLevel::ALL {
+                let compressed = scratch.file(&format!("{}.{}.zst", data.name(), level.label()));
+                compress(&input, &compressed, level, options);
+                let output = scratch.file("out.bin");
+                group.bench_with_input(
+                    BenchmarkId::new(level.label(), &parameter),
+                    &compressed,
+                    |b, compressed| b.iter(|| decompress(compressed, &output, options)),
+                );
+            }
+            group.finish();
+        }
+    }
+
+    // Seekable output splits into independent frames that decompress in parallel
+    let size = *SIZES.last().unwrap();
+    let input = scratch.file("seekable-input");
+    std::fs::write(&input, Data::Text.generate(size)).unwrap();
+    let compressed = scratch.file("seekable.zst");
+    let seekable = JobOptions {
+        seekable_frame_size: Some(256 * 1024),
+        ..options
+    };
+    compress(&input, &compressed, CompressionLevel::Normal, seekable);
+    let mut group = c.benchmark_group("decompress-seekable");
+    group.throughput(Throughput::Bytes(size as u64));
+    for threads in [1, 0] {
+        let name = if threads == 1 {
+            "1 thread"
+        } else {
+            "all cores"
+        };
+        let output = scratch.file("out.bin");
+        let options = JobOptions { threads, ..options };
+        group.bench_function(
+            BenchmarkId::new(name, format!("text/{}KiB", size / 1024)),
+            |b| b.iter(|| decompress(&compressed, &output, options)),
+        );
+    }
+    group.finish();
+
+    // Reading through a buffer against mapping the input (zstd) or letting the
+    // kernel copy it (store), at the size `ReadMode::Auto` starts mapping from
+    let size = MAPPED_MIN_SIZE as usize;
+    let input = scratch.file("read-input");
+    std::fs::write(&input, Data::Text.generate(size)).unwrap();
+    let output = scratch.file("out.zst");
+    let mut group = c.benchmark_group("read");
+    group.throughput(Throughput::Bytes(size as u64));
+    for (name, algorithm) in [("store", Algorithm::Store), ("fast", Algorithm::Zstd)] {
+        for read in [ReadMode::Buffered, ReadMode::Mapped] {
+            let options = JobOptions {
+                algorithm,
+                read,
+                ..options
+            };
+            group.bench_function(BenchmarkId::new(name, read.label()), |b| {
+                b.iter(|| compress(&input, &output, CompressionLevel::Fast, options))
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group! {
+    name = benches;
+    // Jobs at level Best take a while; fewer, longer samples keep the suite to minutes
+    config = Criterion::default()
+        .sample_size(10)
+        .measurement_time(Duration::from_secs(3))
+        .warm_up_time(Duration::from_secs(1));
+    targets = engine
+}
+criterion_main!(benches);
@@ -0,0 +1,88 @@
+//! Writes the seed corpus the fuzz targets start from to `fuzz/seeds/`: one
+//! file of each layout freya reads or writes, plus truncated copies of them.
+//!
+//! cargo run --example fuzz_seeds
+
+use freya::seekable::{SeekTable, SeekableWriter};
+use freya::store::StoreWriter;
+use std::io::{self, Write};
+use std::path::Path;
+
+fn main() -> io::Result<()> {
+    let text = b"Freya compresses files without losing anything.\n".repeat(60);
+    let mut seeds: Vec<(&str, Vec<u8>)> = Vec::new();
+
+    seeds.push(("empty-frame.zst", zstd::bulk::compress(b"", 3)?));
+    seeds.push(("single-frame.zst", zstd::bulk::compress(&text, 3)?));
+    seeds.push(("rle.zst", zstd::bulk::compress(&[0u8; 50_000], 19)?));
+
+    // Streamed frames don't record their size, and this one carries a checksum
+    let mut encoder = zstd::stream::Encoder::new(Vec::new(), 3)?;
+    encoder.include_checksum(true)?;
+    encoder.write_all(&text)?;
+    seeds.push(("streamed-checksum.zst", encoder.finish()?));
+
+    let mut multi = Vec::new();
+    for part in text.chunks(1000) {
+        multi.extend(zstd::bulk::compress(part, 1)?);
+    }
+    seeds.push(("multi-frame.zst", multi));
+
+    let mut skippable = 0x184D_2A50u32.to_le_bytes().to_vec();
+    skippable.extend(4u32.to_le_bytes());
+    skippable.extend(b"meta");
+    skippable.extend(zstd::bulk::compress(&text[..500], 3)?);
+    seeds.push(("skippable-first.zst", skippable));
+
+    let mut writer = SeekableWriter::new(Vec::new(), 3, 1024)?;
+    writer.write_all(&text)?;
+    let seekable = writer.finish()?;
+    seeds.push(("seekable.zst", seekable.clone()));
+
+    let mut writer = SeekableWriter::new(Vec::new(), 3, 1024)?;
+    writer.include_checksum(true);
+    writer.write_all(&text)?;
+    seeds.push(("seekable-checksum.zst", writer.finish()?));
+
+    // The seek table claims far more than the first frame holds
+    let mut inflated = seekable;
+    let table = SeekTable::read_from(&mut io::Cursor::new(&inflated))?;
+    let first_size = table.compressed_len() as usize + 12;
+    inflated[first_size..first_size + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+    seeds.push(("seekable-inflated.zst", inflated));
+
+    let mut writer = StoreWriter::new(Vec::new(), Some(text.len() as u64))?;
+    writer.write_all(&text)?;
+    seeds.push(("stored.zst", writer.finish()?));
+
+    seeds.push((
+        "gzip-header.bin",
+        b"\x1f\x8b\x08\x00\x00\x00\x00\x00".to_vec(),
+    ));
+    seeds.push(("webp-header.bin", b"RIFF\x24\x00\x00\x00WEBPVP8 ".to_vec()));
+    seeds.push(("text.txt", text.clone()));
+
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("fuzz")
+        .join("seeds");
+    std::fs::create_dir_all(&dir)?;
+    for (name, data) in &seeds {
+        std::fs::write(dir.join(name), data)?;
+        if name.ends_with(".zst") && data.len() > 1 {
+            std::fs::write(
+                dir.join(format!("{}.cut-half", name)),
+                &data[..data.len() / 2],
+            )?;
+            std::fs::write(
+                dir.join(format!("{}.cut-last", name)),
+                &data[..data.len() - 1],
+            )?;
+        }
+    }
+    println!(
+        "{} seeds in {}",
+        std::fs::read_dir(&dir)?.count(),
+        dir.display()
+    );
+    Ok(())
+}
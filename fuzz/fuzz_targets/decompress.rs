@@ -0,0 +1,35 @@
+//! The decompression path: files laid out as independent frames are decoded
+//! frame by frame, the way the parallel workers do, everything else by one
+//! streaming decoder.
+
+#![no_main]
+
+use freya::frames::independent_frames;
+use freya::seekable::decode_frame;
+use libfuzzer_sys::fuzz_target;
+use std::io::{self, Cursor, Read};
+use zstd::stream::Decoder;
+
+/// A few bytes can legitimately inflate to gigabytes; stop well before.
+const OUTPUT_LIMIT: u64 = 64 * 1024 * 1024;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(Some(frames)) = independent_frames(Cursor::new(data)) {
+        let mut reader = Cursor::new(data);
+        let mut decoded = 0;
+        for frame in &frames {
+            decoded += frame.decompressed_size as u64;
+            if decoded > OUTPUT_LIMIT {
+                break;
+            }
+            match decode_frame(&mut reader, frame) {
+                Ok(bytes) => assert_eq!(bytes.len(), frame.decompressed_size as usize),
+                Err(_) => break,
+            }
+        }
+    }
+
+    if let Ok(decoder) = Decoder::new(data) {
+        let _ = io::copy(&mut decoder.take(OUTPUT_LIMIT), &mut io::sink());
+    }
+});
@@ -0,0 +1,66 @@
+//! The whole decompression job on files laid out as independent frames. The
+//! sizes the frames claim decide where each one lands in the output, so a file
+//! lying about them has to fail without leaving an output behind, and a job
+//! that finishes has to write exactly what was claimed.
+
+#![no_main]
+
+use freya::frames::independent_frames;
+use freya::{CompressMessage, JobOptions, MAX_EXPANSION, start_decompression};
+use libfuzzer_sys::fuzz_target;
+use std::io::Cursor;
+use std::sync::mpsc;
+
+/// Genuine frames can inflate this far; don't spend the run writing them out.
+const OUTPUT_LIMIT: u64 = 64 * 1024 * 1024;
+
+fuzz_target!(|data: &[u8]| {
+    // A single frame goes through the streaming decoder, which `decompress` covers
+    let Ok(Some(frames)) = independent_frames(Cursor::new(data)) else {
+        return;
+    };
+    if frames.len() < 2 {
+        return;
+    }
+    let claimed: u64 = frames.iter().map(|f| f.decompressed_size as u64).sum();
+    let plausible = frames
+        .iter()
+        .all(|f| f.decompressed_size as u64 <= f.compressed_size as u64 * MAX_EXPANSION);
+    if plausible && claimed > OUTPUT_LIMIT {
+        return;
+    }
+
+    let dir = std::env::temp_dir().join(format!("freya_fuzz_job_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let (input, output) = (dir.join("input.zst"), dir.join("input"));
+    std::fs::write(&input, data).unwrap();
+
+    let (tx, rx) = mpsc::channel();
+    start_decompression(
+        input.to_string_lossy().to_string(),
+        output.to_string_lossy().to_string(),
+        tx,
+        JobOptions {
+            threads: 4,
+            sparse: data.len() % 2 == 0,
+            ..JobOptions::default()
+        },
+    );
+    for msg in rx {
+        match msg {
+            CompressMessage::Finished {
+                compressed_size, ..
+            } => {
+                assert_eq!(compressed_size, claimed);
+                assert_eq!(std::fs::metadata(&output).unwrap().len(), claimed);
+                break;
+            }
+            CompressMessage::Error(_) => {
+                assert!(!output.exists(), "failed job left its output behind");
+                break;
+            }
+            _ => {}
+        }
+    }
+    std::fs::remove_dir_all(&dir).ok();
+});
@@ -0,0 +1,26 @@
+//! Frame header parsing and the frame scanner behind `info` and the parallel
+//! decompression layout.
+
+#![no_main]
+
+use freya::frames::{FrameScanner, read_frame_header};
+use libfuzzer_sys::fuzz_target;
+use std::io::Cursor;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = read_frame_header(&mut &data[..]);
+
+    let Ok(scanner) = FrameScanner::new(Cursor::new(data)) else {
+        return;
+    };
+    let mut end = 0;
+    for frame in scanner {
+        let Ok(frame) = frame else {
+            break;
+        };
+        // Frames follow each other and never reach past the input
+        assert_eq!(frame.offset, end);
+        end += frame.compressed_size;
+        assert!(end <= data.len() as u64);
+    }
+});
@@ -0,0 +1,15 @@
+//! `freya info`: everything it reads from a file, and everything it prints.
+
+#![no_main]
+
+use freya::info::FileInfo;
+use libfuzzer_sys::fuzz_target;
+use std::io::Cursor;
+use std::path::PathBuf;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(info) = FileInfo::from_reader(PathBuf::from("fuzz.zst"), Cursor::new(data)) {
+        let _ = info.to_string();
+        let _ = info.frame_lines();
+    }
+});
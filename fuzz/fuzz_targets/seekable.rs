@@ -0,0 +1,36 @@
+//! The seek table at the end of seekable files, and reading through it.
+
+#![no_main]
+
+use freya::seekable::{SeekTable, SeekableReader};
+use libfuzzer_sys::fuzz_target;
+use std::io::{Cursor, Read, Seek, SeekFrom};
+
+/// Larger claims are real allocations; leave them to the table checks.
+const OUTPUT_LIMIT: u64 = 16 * 1024 * 1024;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(table) = SeekTable::read_from(&mut Cursor::new(data)) else {
+        return;
+    };
+    // A table is only accepted if its frames exactly fill the rest of the file
+    assert_eq!(
+        table.compressed_len() + table.encoded_len(),
+        data.len() as u64
+    );
+    if table.decompressed_len() > OUTPUT_LIMIT {
+        return;
+    }
+
+    let mut reader = SeekableReader::new(Cursor::new(data)).expect("table read above");
+    let mut contents = Vec::new();
+    if reader.read_to_end(&mut contents).is_ok() {
+        assert_eq!(contents.len() as u64, reader.len());
+        // Reading from the middle gives the same bytes
+        let middle = reader.len() / 2;
+        reader.seek(SeekFrom::Start(middle)).unwrap();
+        let mut tail = Vec::new();
+        reader.read_to_end(&mut tail).unwrap();
+        assert_eq!(tail, contents[middle as usize..]);
+    }
+});
@@ -0,0 +1,12 @@
+//! Recognising already compressed content from its first bytes.
+
+#![no_main]
+
+use freya::sniff::{entropy, sniff};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = sniff(data);
+    let bits = entropy(data);
+    assert!((0.0..=8.0 + 1e-9).contains(&bits), "entropy {}", bits);
+});
@@ -1,7 +1,32 @@
-use crate::{CompressMessage, CompressionLevel};
-use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
+use crate::batch::{self, CompressedPolicy};
+use crate::bench::{self, BenchMessage, LevelResult};
+use crate::config::Config;
+use crate::estimate::{self, Estimate};
+use crate::help::Context;
+use crate::history::{self, History, HistoryEntry, HistoryView, JobKind, JobStart};
+use crate::input::{self, Clock, EventSource, FilePicker, NativePicker, SystemClock, Terminal};
+use crate::keymap::{Action, KeyChord, KeySequence, Keymap};
+use crate::mouse::{Target, Targets};
+use crate::output::create_parent;
+use crate::progress::{ProgressCounter, ProgressMode};
+use crate::report::JobResult;
+use crate::settings::{Setting, SettingsView};
+use crate::theme::{self, Theme};
+use crate::ui::LayoutMode;
+use crate::{CompressMessage, CompressionLevel, JobOptions, info::FileInfo};
+use crossterm::event::{
+    DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyEventKind, MouseButton,
+    MouseEvent, MouseEventKind,
+};
 use ratatui::{DefaultTerminal, Frame};
-use std::{io, sync::mpsc};
+use std::{io, path::PathBuf, sync::mpsc, time::Duration};
+
+/// Status messages kept for the dashboard's log.
+const LOG_LINES: usize = 200;
+/// Jobs from the history file shown on the dashboard.
+const RECENT_JOBS: usize = 100;
+/// How long a finished job stays on screen before the app exits by itself.
+const AUTO_EXIT_DELAY: Duration = Duration::from_secs(2);
 
 #[derive(Debug)]
 pub struct App {
@@ -11,13 +36,90 @@ pub struct App {
     pub progress: f64, // A percentage from 0.0 to 1.0
     pub status_message: String,
     pub receiver: Option<mpsc::Receiver<CompressMessage>>,
+    /// Shared with the running job, which updates it instead of sending progress.
+    pub progress_counter: Option<ProgressCounter>,
+    /// Stop asked for; the job is recorded once it confirms by failing.
+    pub stopping: bool,
     pub last_compression_result: Option<String>,
+    /// The same outcome in structured form, printed on exit with `--json`.
+    pub last_result: Option<JobResult>,
     pub compression_finished_at: Option<std::time::Instant>,
     pub compression_level: CompressionLevel,
+    pub job_options: JobOptions,
+    /// What folder jobs do with files that are already compressed.
+    pub compressed_policy: CompressedPolicy,
+    /// Panel filling the space below the status block, closed with Esc.
+    pub panel: Option<Panel>,
+    pub bench_receiver: Option<mpsc::Receiver<BenchMessage>>,
+    /// File picked for compression, waiting for the user to confirm with Enter.
+    pub pending: Option<PendingJob>,
+    pub estimate_receiver: Option<mpsc::Receiver<Result<Estimate, String>>>,
+    /// Defaults the app started with (config file plus environment).
+    pub config: Config,
+    /// The config file alone, without environment overrides; settings changes
+    /// are saved from this, so an override never ends up in the file.
+    file_config: Config,
+    /// The job in progress, recorded to the history when it ends.
+    pub running_job: Option<JobStart>,
+    /// The `?` overlay listing every key binding.
+    pub show_help: bool,
+    pub keymap: Keymap,
+    pub theme: Theme,
+    /// Keys typed so far of a multi-key binding.
+    pending_keys: KeySequence,
+    /// Where the last frame put the things a click can hit.
+    pub click_targets: Targets,
+    /// The layout of the last frame, following the terminal size.
+    pub layout: LayoutMode,
+    /// Every status message with its Unix time, for the dashboard's log.
+    pub log: Vec<(u64, String)>,
+    /// The latest jobs from the history file, read once the dashboard shows them.
+    pub recent: Option<Vec<HistoryEntry>>,
+    events: Box<dyn EventSource>,
+    picker: Box<dyn FilePicker>,
+    clock: Box<dyn Clock>,
+    /// Where jobs are recorded; `None` on platforms without a data directory.
+    history: Option<History>,
+}
+
+#[derive(Debug)]
+pub struct PendingJob {
+    pub input_path: PathBuf,
+    pub estimate: Option<Estimate>,
+}
+
+#[derive(Debug)]
+pub enum Panel {
+    Info(FileInfo),
+    Bench(Benchmark),
+    Settings(SettingsView),
+    History(HistoryView),
+}
+
+/// State of the level comparison panel.
+#[derive(Debug, Default)]
+pub struct Benchmark {
+    pub file_name: String,
+    pub results: Vec<LevelResult>,
+    /// Row highlighted in the results table; Enter applies its level.
+    pub selected: usize,
+    /// Level currently being measured, while the benchmark runs.
+    pub running: Option<CompressionLevel>,
+    /// Bytes actually benchmarked and the full file size, once done.
+    pub sample: Option<(u64, u64)>,
 }
 
 impl Default for App {
     fn default() -> Self {
+        Self::new(Config::default())
+    }
+}
+
+impl App {
+    pub fn new(config: Config) -> Self {
+        // `Config::load` already rejected a bad `[keys]` table or theme
+        let keymap = Keymap::from_config(&config.keys).unwrap_or_default();
+        let theme = config.theme(theme::no_color()).unwrap_or_default();
         Self {
             exit: false,
             is_compressing: false,
@@ -25,46 +127,114 @@ impl Default for App {
             progress: 0.0,
             status_message: " Press 'o' to compress or 'd' to decompress a file".to_string(),
             receiver: None,
+            progress_counter: None,
+            stopping: false,
             last_compression_result: None,
+            last_result: None,
             compression_finished_at: None,
-            compression_level: CompressionLevel::Normal,
+            compression_level: config.level,
+            job_options: config.job_options(),
+            compressed_policy: config.on_compressed,
+            panel: None,
+            bench_receiver: None,
+            pending: None,
+            estimate_receiver: None,
+            file_config: config.clone(),
+            config,
+            running_job: None,
+            show_help: false,
+            keymap,
+            theme,
+            pending_keys: KeySequence::new(),
+            click_targets: Targets::default(),
+            layout: LayoutMode::default(),
+            log: Vec::new(),
+            recent: None,
+            events: Box::new(Terminal),
+            picker: Box::new(NativePicker),
+            clock: Box::new(SystemClock),
+            history: History::open_default(),
         }
     }
-}
 
-impl App {
+    /// The config file as read, before `config` had the environment applied.
+    pub fn with_file_config(mut self, file_config: Config) -> Self {
+        self.file_config = file_config;
+        self
+    }
+
+    /// Records jobs to `history` instead of the default history file.
+    pub fn with_history(mut self, history: History) -> Self {
+        self.history = Some(history);
+        self
+    }
+
+    /// Reads events from `events` instead of the terminal.
+    pub fn with_events(mut self, events: impl EventSource + 'static) -> Self {
+        self.events = Box::new(events);
+        self
+    }
+
+    /// Asks `picker` for files instead of opening the native dialogs.
+    pub fn with_picker(mut self, picker: impl FilePicker + 'static) -> Self {
+        self.picker = Box::new(picker);
+        self
+    }
+
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Box::new(clock);
+        self
+    }
+
+    /// Whether the user quit or the auto-exit delay ran out.
+    pub fn should_exit(&self) -> bool {
+        self.exit
+    }
+
     /// runs the application's main loop until the user quits
     pub fn run(&mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
-        while !self.exit {
-            terminal.draw(|frame| self.draw(frame))?;
-            self.handle_events()?;
+        crossterm::execute!(io::stdout(), EnableMouseCapture)?;
+        let mut result = Ok(());
+        while !self.exit && result.is_ok() {
+            result = terminal
+                .draw(|frame| self.draw(frame))
+                .and_then(|_| self.handle_events());
         }
-        Ok(())
+        // Restoring the terminal doesn't turn mouse reporting off by itself
+        crossterm::execute!(io::stdout(), DisableMouseCapture)?;
+        result
     }
 
     fn draw(&mut self, frame: &mut Frame) {
         frame.render_widget(self, frame.area());
     }
 
-    fn handle_events(&mut self) -> io::Result<()> {
-        // Use poll with a timeout so the loop can also check compression progress
-        if event::poll(std::time::Duration::from_millis(50))? {
-            match event::read()? {
+    /// One turn of the main loop without the drawing: handles the next event
+    /// (waiting up to 50 ms for one), then the background jobs' progress.
+    pub fn handle_events(&mut self) -> io::Result<()> {
+        // Use a timeout so the loop can also check compression progress
+        if let Some(event) = self.events.next(Duration::from_millis(50))? {
+            match event {
                 // it's important to check that the event is a key press event as
                 // crossterm also emits key release and repeat events on Windows.
                 Event::Key(key_event) if key_event.kind == KeyEventKind::Press => {
                     self.handle_key_event(key_event)
                 }
+                Event::Mouse(mouse_event) => self.handle_mouse_event(mouse_event),
                 _ => {}
             };
         }
         self.check_compression_progress();
+        self.check_bench_progress();
+        self.check_estimate();
+        self.update_dashboard();
 
         // Handle the 2-second auto-exit delay
-        if let Some(finished_at) = self.compression_finished_at {
-            if finished_at.elapsed() >= std::time::Duration::from_secs(2) {
-                self.exit();
-            }
+        if self.config.auto_exit
+            && let Some(finished_at) = self.compression_finished_at
+            && self.clock.now().saturating_duration_since(finished_at) >= AUTO_EXIT_DELAY
+        {
+            self.exit();
         }
 
         Ok(())
@@ -82,49 +252,441 @@ impl App {
                             self.progress = bytes_processed as f64 / total_bytes as f64;
                         }
                     }
+                    // Dropping the counter right away is what stops the job
+                    CompressMessage::Counter(counter) if !self.stopping => {
+                        self.progress_counter = Some(counter)
+                    }
+                    CompressMessage::Counter(_) => {}
                     CompressMessage::Finished {
                         original_size,
                         compressed_size,
                         output_path,
+                        source_removed,
                     } => {
                         self.is_compressing = false;
+                        self.stopping = false;
                         self.progress = 1.0;
                         self.receiver = None;
+                        self.progress_counter = None;
 
-                        if self.is_decompressing {
-                            self.is_decompressing = false;
-                            self.status_message = " Decompression complete!".to_string();
-                            self.last_compression_result = Some(format!(
-                                "\nDecompression successful!\nSaved to: {}\nCompressed: {} bytes\nDecompressed: {} bytes\n",
-                                output_path, original_size, compressed_size
-                            ));
+                        self.status_message = if self.is_decompressing {
+                            " Decompression complete!".to_string()
                         } else {
-                            let ratio = if original_size > 0 {
-                                (compressed_size as f64 / original_size as f64) * 100.0
-                            } else {
-                                0.0
-                            };
-
-                            self.status_message = " Compression complete!".to_string();
-                            self.last_compression_result = Some(format!(
-                                "\nCompression successful!\nSaved to: {}\nOriginal: {} bytes\nCompressed: {} bytes ({:.2}% of original)\n",
-                                output_path, original_size, compressed_size, ratio
-                            ));
+                            " Compression complete!".to_string()
+                        };
+                        self.last_compression_result = Some(result_summary(
+                            self.is_decompressing,
+                            original_size,
+                            compressed_size,
+                            &output_path,
+                            source_removed,
+                        ));
+                        self.is_decompressing = false;
+                        if let Some(job) = self.running_job.take() {
+                            let result = JobResult::finished(
+                                &job,
+                                original_size,
+                                compressed_size,
+                                PathBuf::from(&output_path),
+                                source_removed,
+                            );
+                            self.record(vec![result.entry.clone()]);
+                            self.last_result = Some(result);
                         }
 
-                        self.compression_finished_at = Some(std::time::Instant::now());
+                        self.compression_finished_at = Some(self.clock.now());
+                        return;
+                    }
+                    CompressMessage::BatchFinished(report) => {
+                        self.is_compressing = false;
+                        self.stopping = false;
+                        self.progress = 1.0;
+                        self.receiver = None;
+                        self.progress_counter = None;
+                        self.status_message = format!(" Batch complete: {}", report.counts());
+                        self.last_compression_result = Some(report.summary());
+                        if let Some(job) = self.running_job.take() {
+                            let level = job.level.unwrap_or(self.compression_level);
+                            self.record(HistoryEntry::from_batch(&report, level));
+                            self.last_result = Some(JobResult::batch(&job, &report));
+                        }
+                        self.compression_finished_at = Some(self.clock.now());
                         return;
                     }
                     CompressMessage::Error(e) => {
                         self.is_compressing = false;
                         self.is_decompressing = false;
                         self.progress = 0.0;
-                        self.status_message = format!(" Error: {}", e);
+                        self.status_message = if std::mem::take(&mut self.stopping) {
+                            " Job stopped".to_string()
+                        } else {
+                            format!(" Error: {}", e)
+                        };
                         self.receiver = None;
+                        self.progress_counter = None;
+                        if let Some(job) = self.running_job.take() {
+                            let result = JobResult::failed(&job, &e);
+                            self.record(vec![result.entry.clone()]);
+                            self.last_result = Some(result);
+                        }
                         return;
                     }
                 }
             }
+            if let Some(counter) = &self.progress_counter {
+                self.progress = counter.fraction();
+            }
+        }
+    }
+
+    // The TUI reads progress once per tick, so its jobs share a counter with it
+    // rather than sending messages it would mostly skip.
+    fn watched_options(&self) -> JobOptions {
+        JobOptions {
+            progress: ProgressMode::Counter,
+            ..self.job_options
+        }
+    }
+
+    fn check_bench_progress(&mut self) {
+        let Some(receiver) = &self.bench_receiver else {
+            return;
+        };
+        while let Ok(msg) = receiver.try_recv() {
+            let Some(Panel::Bench(bench)) = &mut self.panel else {
+                // The panel was closed while the benchmark ran; drop its results
+                self.bench_receiver = None;
+                return;
+            };
+            match msg {
+                BenchMessage::Running(level) => {
+                    bench.running = Some(level);
+                    self.status_message = format!(" Benchmarking {} ...", level.label());
+                }
+                BenchMessage::Result(result) => bench.results.push(result),
+                BenchMessage::Finished {
+                    sample_size,
+                    file_size,
+                } => {
+                    bench.running = None;
+                    bench.sample = Some((sample_size, file_size));
+                    self.status_message =
+                        " Benchmark done: pick a level with ↑/↓ and press Enter".to_string();
+                    self.bench_receiver = None;
+                    return;
+                }
+                BenchMessage::Error(e) => {
+                    bench.running = None;
+                    self.status_message = format!(" Error: {}", e);
+                    self.bench_receiver = None;
+                    return;
+                }
+            }
+        }
+    }
+
+    fn check_estimate(&mut self) {
+        let Some(receiver) = &self.estimate_receiver else {
+            return;
+        };
+        let Ok(result) = receiver.try_recv() else {
+            return;
+        };
+        self.estimate_receiver = None;
+        let Some(pending) = &mut self.pending else {
+            return;
+        };
+        let name = pending.input_path.file_name().unwrap_or_default();
+        match result {
+            Ok(estimate) => {
+                self.status_message = format!(
+                    " {:?}: {} | Enter to compress, Esc to cancel",
+                    name,
+                    estimate.summary()
+                );
+                pending.estimate = Some(estimate);
+            }
+            Err(e) => {
+                self.status_message = format!(
+                    " {:?}: no estimate ({}) | Enter to compress, Esc to cancel",
+                    name, e
+                );
+            }
+        }
+    }
+
+    // (Re)starts the estimate for the pending file at the current level.
+    fn start_estimate(&mut self) {
+        let Some(pending) = &mut self.pending else {
+            return;
+        };
+        pending.estimate = None;
+        let (tx, rx) = mpsc::channel();
+        self.estimate_receiver = Some(rx);
+        self.status_message = format!(
+            " Estimating {:?} at {} ...",
+            pending.input_path.file_name().unwrap_or_default(),
+            self.compression_level.label()
+        );
+        estimate::start_estimate(
+            pending.input_path.to_string_lossy().to_string(),
+            self.compression_level,
+            tx,
+        );
+    }
+
+    /// Where the pending file would be written with the current settings.
+    pub fn pending_output(&self) -> Option<Result<PathBuf, String>> {
+        let pending = self.pending.as_ref()?;
+        Some(self.config.naming().compressed(&pending.input_path))
+    }
+
+    fn start_compression_job(&mut self, input_path: PathBuf, output_path: PathBuf) {
+        if let Err(e) = create_parent(&output_path) {
+            self.status_message = format!(" Error: {}", e);
+            return;
+        }
+
+        // Set up the communication channel for the background thread
+        let (tx, rx) = std::sync::mpsc::channel();
+        self.receiver = Some(rx);
+        self.is_compressing = true;
+        self.progress = 0.0;
+        self.compression_finished_at = None;
+
+        // Let the user know we're starting
+        self.status_message = format!(
+            " Compressing {:?} [{}]",
+            input_path.file_name().unwrap_or_default(),
+            self.compression_level.label(),
+        );
+
+        self.running_job = Some(JobStart::new(
+            JobKind::Compress,
+            input_path.clone(),
+            Some(output_path.clone()),
+            Some(self.compression_level),
+        ));
+        crate::start_compression(
+            input_path.to_string_lossy().to_string(),
+            output_path.to_string_lossy().to_string(),
+            tx,
+            self.compression_level,
+            self.watched_options(),
+        );
+    }
+
+    fn start_batch_job(&mut self, dir: PathBuf) {
+        let (tx, rx) = mpsc::channel();
+        self.receiver = Some(rx);
+        self.is_compressing = true;
+        self.progress = 0.0;
+        self.compression_finished_at = None;
+        self.status_message = format!(
+            " Compressing folder {:?} [{}, already compressed: {}]",
+            dir.file_name().unwrap_or_default(),
+            self.compression_level.label(),
+            self.compressed_policy.label(),
+        );
+        self.running_job = Some(JobStart::new(
+            JobKind::Compress,
+            dir.clone(),
+            None,
+            Some(self.compression_level),
+        ));
+        batch::start_batch(
+            dir,
+            self.config.naming(),
+            tx,
+            self.compression_level,
+            self.watched_options(),
+            self.compressed_policy,
+        );
+    }
+
+    // Keeps the log and the recent jobs the dashboard shows up to date.
+    fn update_dashboard(&mut self) {
+        if self
+            .log
+            .last()
+            .is_none_or(|(_, message)| *message != self.status_message)
+        {
+            if self.log.len() == LOG_LINES {
+                self.log.remove(0);
+            }
+            self.log
+                .push((history::unix_now(), self.status_message.clone()));
+        }
+        if self.layout == LayoutMode::Dashboard && self.recent.is_none() {
+            let mut entries = self
+                .history
+                .as_ref()
+                .and_then(|history| history.load().ok())
+                .unwrap_or_default();
+            entries.drain(..entries.len().saturating_sub(RECENT_JOBS));
+            self.recent = Some(entries);
+        }
+    }
+
+    // Appends finished jobs to the history file. A failure to save is shown but
+    // doesn't change the job's outcome.
+    fn record(&mut self, entries: Vec<HistoryEntry>) {
+        let Some(history) = &self.history else {
+            return;
+        };
+        if let Err(e) = history.append(&entries) {
+            self.status_message
+                .push_str(&format!(" (not saved to history: {})", e));
+        }
+        if let Some(recent) = &mut self.recent {
+            recent.extend(entries);
+        }
+    }
+
+    fn history_mut(&mut self) -> Option<&mut HistoryView> {
+        match &mut self.panel {
+            Some(Panel::History(view)) => Some(view),
+            _ => None,
+        }
+    }
+
+    fn open_history(&mut self) {
+        match self.history.as_ref().map(History::load).transpose() {
+            Ok(entries) => {
+                let entries = entries.unwrap_or_default();
+                self.status_message = format!(" History: {} jobs", entries.len());
+                self.panel = Some(Panel::History(HistoryView::new(entries)));
+            }
+            Err(e) => self.status_message = format!(" Error: could not read history: {}", e),
+        }
+    }
+
+    // Keys while the history filter is being typed; it applies as you type.
+    fn handle_filter_key(&mut self, key_event: KeyEvent) {
+        let Some(view) = self.history_mut() else {
+            return;
+        };
+        match key_event.code {
+            KeyCode::Char(c) => view.filter.push(c),
+            KeyCode::Backspace => {
+                view.filter.pop();
+            }
+            KeyCode::Esc => {
+                view.filter.clear();
+                view.filtering = false;
+            }
+            KeyCode::Enter => view.filtering = false,
+            _ => {}
+        }
+        view.selected = 0;
+    }
+
+    /// What keys currently act on, for the footer hints. Typing and the help
+    /// overlay capture every key; a running job limits what the rest can do.
+    pub fn context(&self) -> Context {
+        match &self.panel {
+            Some(Panel::Settings(view)) if view.editing.is_some() => return Context::EditSetting,
+            Some(Panel::History(view)) if view.filtering => return Context::FilterHistory,
+            _ => {}
+        }
+        if self.show_help {
+            return Context::Help;
+        }
+        if self.is_compressing {
+            return Context::Running;
+        }
+        match &self.panel {
+            Some(Panel::Info(_)) => Context::Info,
+            Some(Panel::Bench(_)) => Context::Bench,
+            Some(Panel::Settings(_)) => Context::Settings,
+            Some(Panel::History(_)) => Context::History,
+            None if self.pending.is_some() => Context::Pending,
+            None => Context::Main,
+        }
+    }
+
+    fn settings_mut(&mut self) -> Option<&mut SettingsView> {
+        match &mut self.panel {
+            Some(Panel::Settings(view)) => Some(view),
+            _ => None,
+        }
+    }
+
+    fn open_settings(&mut self) {
+        // Start from what's in effect, including changes made on the main screen
+        self.config.level = self.compression_level;
+        self.config.remove_source = self.job_options.remove_source;
+        self.panel = Some(Panel::Settings(SettingsView::default()));
+        self.status_message = " Settings: changes are saved as you make them".to_string();
+    }
+
+    // Runs `change` on the config; on success applies it and saves `setting` to
+    // the config file, otherwise shows the error under the settings list.
+    fn change_setting(
+        &mut self,
+        setting: Setting,
+        change: impl FnOnce(&mut crate::config::Config) -> Result<(), String>,
+    ) {
+        let mut config = self.config.clone();
+        let mut file_config = self.file_config.clone();
+        let result = change(&mut config).and_then(|()| {
+            setting.copy(&config, &mut file_config);
+            file_config
+                .save()
+                .map_err(|e| format!("could not save settings: {}", e))
+        });
+        match result {
+            Ok(path) => {
+                self.config = config;
+                self.file_config = file_config;
+                self.compression_level = self.config.level;
+                self.job_options = self.config.job_options();
+                self.compressed_policy = self.config.on_compressed;
+                self.theme = self.config.theme(theme::no_color()).unwrap_or_default();
+                self.status_message = format!(" Settings saved to {}", path.display());
+                if let Some(view) = self.settings_mut() {
+                    view.error = None;
+                    view.editing = None;
+                }
+            }
+            Err(e) => {
+                if let Some(view) = self.settings_mut() {
+                    view.error = Some(e);
+                }
+            }
+        }
+    }
+
+    // Keys while a text setting is being typed; nothing else reacts until Enter or Esc.
+    fn handle_edit_key(&mut self, key_event: KeyEvent) {
+        let Some(view) = self.settings_mut() else {
+            return;
+        };
+        let setting = view.setting();
+        let Some(text) = &mut view.editing else {
+            return;
+        };
+        match key_event.code {
+            KeyCode::Char(c) => text.push(c),
+            KeyCode::Backspace => {
+                text.pop();
+            }
+            KeyCode::Esc => {
+                view.editing = None;
+                view.error = None;
+            }
+            KeyCode::Enter => {
+                let text = text.clone();
+                self.change_setting(setting, |config| setting.set_text(config, &text));
+            }
+            _ => {}
+        }
+    }
+
+    fn bench_mut(&mut self) -> Option<&mut Benchmark> {
+        match &mut self.panel {
+            Some(Panel::Bench(bench)) => Some(bench),
+            _ => None,
         }
     }
 
@@ -133,26 +695,256 @@ impl App {
             self.progress = 0.0;
         }
 
-        match key_event.code {
-            KeyCode::Char('q') => self.exit(),
-            // Up arrow → decrease toward Fast (slower = smaller file, so intuitive "up = better")
-            KeyCode::Up => {
-                if !self.is_compressing {
-                    self.compression_level = self.compression_level.decrease();
+        if self
+            .settings_mut()
+            .is_some_and(|view| view.editing.is_some())
+        {
+            self.handle_edit_key(key_event);
+            return;
+        }
+        if self.history_mut().is_some_and(|view| view.filtering) {
+            self.handle_filter_key(key_event);
+            return;
+        }
+
+        let key = KeyChord::from_event(&key_event);
+        if let Some(action) = self.keymap.resolve(&mut self.pending_keys, key) {
+            self.handle_action(action);
+        }
+    }
+
+    // Clicks go through the same actions as keys; the wheel scrolls the open list.
+    fn handle_mouse_event(&mut self, mouse_event: MouseEvent) {
+        if matches!(
+            self.context(),
+            Context::EditSetting | Context::FilterHistory
+        ) {
+            return;
+        }
+        let scrolls = matches!(
+            self.panel,
+            Some(Panel::Bench(_) | Panel::Settings(_) | Panel::History(_))
+        ) && !self.show_help;
+        match mouse_event.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                self.pending_keys.clear();
+                match self.click_targets.at(mouse_event.column, mouse_event.row) {
+                    Some(Target::Action(action)) => self.handle_action(action),
+                    Some(Target::Level(level)) if !self.is_compressing => self.select_level(level),
+                    Some(Target::Row(index)) => self.click_row(index),
+                    _ => {}
+                }
+            }
+            MouseEventKind::ScrollUp if scrolls => self.handle_action(Action::Up),
+            MouseEventKind::ScrollDown if scrolls => self.handle_action(Action::Down),
+            _ => {}
+        }
+    }
+
+    // Picks a row of the open panel's list; a click on the picked row is Enter,
+    // except in the history, where Enter does nothing.
+    fn click_row(&mut self, index: usize) {
+        let selected = match &mut self.panel {
+            Some(Panel::Settings(view)) => {
+                view.error = None;
+                &mut view.selected
+            }
+            Some(Panel::Bench(bench)) => &mut bench.selected,
+            Some(Panel::History(view)) => &mut view.selected,
+            _ => return,
+        };
+        if *selected != index {
+            *selected = index;
+        } else if self.history_mut().is_none() {
+            self.handle_action(Action::Confirm);
+        }
+    }
+
+    fn select_level(&mut self, level: CompressionLevel) {
+        if level != self.compression_level {
+            self.compression_level = level;
+            self.start_estimate();
+        }
+    }
+
+    // Dropping the shared counter makes the worker stop at its next report or
+    // check, delete the partial output and fail with "stopped", which is when
+    // the job is recorded. A job that finishes meanwhile is kept.
+    fn stop_job(&mut self) {
+        self.check_compression_progress();
+        if !self.is_compressing || self.stopping {
+            return;
+        }
+        self.stopping = true;
+        self.progress_counter = None;
+        self.status_message = " Stopping...".to_string();
+    }
+
+    /// Does what `action` means in the current context.
+    pub fn handle_action(&mut self, action: Action) {
+        if self.show_help {
+            match action {
+                Action::Help | Action::Cancel => self.show_help = false,
+                Action::Quit => self.exit(),
+                _ => {}
+            }
+            return;
+        }
+
+        match action {
+            Action::Quit => self.exit(),
+            Action::Help => self.show_help = true,
+            Action::Stop if self.is_compressing => self.stop_job(),
+            Action::Settings if self.settings_mut().is_some() => self.panel = None,
+            Action::Settings => self.open_settings(),
+            Action::History if self.history_mut().is_some() => self.panel = None,
+            Action::History => self.open_history(),
+            // While the history panel is open the arrows scroll and ←/→ change the sort
+            Action::Up if self.history_mut().is_some() => {
+                let view = self.history_mut().expect("checked above");
+                view.selected = view.selected.saturating_sub(1);
+            }
+            Action::Down if self.history_mut().is_some() => {
+                let view = self.history_mut().expect("checked above");
+                view.selected = (view.selected + 1).min(view.visible().len().saturating_sub(1));
+            }
+            Action::Left | Action::Right if self.history_mut().is_some() => {
+                let view = self.history_mut().expect("checked above");
+                view.sort = if action == Action::Right {
+                    view.sort.next()
+                } else {
+                    view.sort.previous()
+                };
+                view.selected = 0;
+            }
+            Action::Filter if self.history_mut().is_some() => {
+                self.history_mut().expect("checked above").filtering = true;
+            }
+            // While the settings panel is open the arrows move through and change its options
+            Action::Up if self.settings_mut().is_some() => {
+                let view = self.settings_mut().expect("checked above");
+                view.selected = view.selected.saturating_sub(1);
+                view.error = None;
+            }
+            Action::Down if self.settings_mut().is_some() => {
+                let view = self.settings_mut().expect("checked above");
+                view.selected = (view.selected + 1).min(Setting::ALL.len() - 1);
+                view.error = None;
+            }
+            Action::Left | Action::Right if self.settings_mut().is_some() => {
+                let setting = self.settings_mut().expect("checked above").setting();
+                let forward = action == Action::Right;
+                // Text settings change only through the editor, so nothing is saved here
+                if !setting.is_text() {
+                    self.change_setting(setting, |config| setting.adjust(config, forward));
+                }
+            }
+            Action::Confirm if self.settings_mut().is_some() => {
+                let setting = self.settings_mut().expect("checked above").setting();
+                if setting.is_text() {
+                    let text = setting.text(&self.config);
+                    self.settings_mut().expect("checked above").editing = Some(text);
+                } else {
+                    self.change_setting(setting, |config| setting.adjust(config, true));
+                }
+            }
+            // While the benchmark panel is open the arrows move through its results
+            Action::Up if self.bench_mut().is_some() => {
+                let bench = self.bench_mut().expect("checked above");
+                bench.selected = bench.selected.saturating_sub(1);
+            }
+            Action::Down if self.bench_mut().is_some() => {
+                let bench = self.bench_mut().expect("checked above");
+                bench.selected = (bench.selected + 1).min(bench.results.len().saturating_sub(1));
+            }
+            Action::Confirm if self.bench_mut().is_some() && !self.is_compressing => {
+                let bench = self.bench_mut().expect("checked above");
+                if let Some(result) = bench.results.get(bench.selected).copied() {
+                    self.compression_level = result.level;
+                    self.status_message = format!(" Level set to {}", result.level.label());
+                    self.panel = None;
                 }
             }
+            // Up arrow → decrease toward Fast (slower = smaller file, so intuitive "up = better")
+            Action::Up if !self.is_compressing => {
+                self.select_level(self.compression_level.decrease());
+            }
             // Down arrow → increase toward Best
-            KeyCode::Down => {
-                if !self.is_compressing {
-                    self.compression_level = self.compression_level.increase();
+            Action::Down if !self.is_compressing => {
+                self.select_level(self.compression_level.increase());
+            }
+            Action::Confirm if !self.is_compressing && self.pending.is_some() => {
+                match self.pending_output().expect("checked above") {
+                    Ok(output_path) => {
+                        let pending = self.pending.take().expect("checked above");
+                        self.estimate_receiver = None;
+                        self.start_compression_job(pending.input_path, output_path);
+                    }
+                    // Stay pending so the template can be fixed in the settings
+                    Err(e) => self.status_message = format!(" Error: {}", e),
+                }
+            }
+            Action::ToggleRemoveSource if !self.is_compressing => {
+                self.job_options.remove_source = !self.job_options.remove_source;
+                self.status_message = if self.job_options.remove_source {
+                    " Source files will be removed after a successful job".to_string()
+                } else {
+                    " Source files will be kept".to_string()
+                };
+            }
+            Action::Cancel => {
+                if self.pending.take().is_some() {
+                    self.estimate_receiver = None;
+                    self.status_message = " Compression cancelled".to_string();
+                }
+                self.panel = None;
+                self.bench_receiver = None;
+            }
+            Action::Benchmark if self.bench_receiver.is_none() => {
+                if let Some(path) = self.picker.pick_file(None) {
+                    let (tx, rx) = mpsc::channel();
+                    self.bench_receiver = Some(rx);
+                    self.panel = Some(Panel::Bench(Benchmark {
+                        file_name: path
+                            .file_name()
+                            .unwrap_or_default()
+                            .to_string_lossy()
+                            .to_string(),
+                        ..Benchmark::default()
+                    }));
+                    self.status_message = " Loading sample ...".to_string();
+                    bench::start_benchmark(
+                        path.to_string_lossy().to_string(),
+                        CompressionLevel::ALL.to_vec(),
+                        bench::DEFAULT_SAMPLE_SIZE,
+                        tx,
+                    );
+                }
+            }
+            Action::Info => {
+                if let Some(path) = self.picker.pick_file(Some(input::ZSTD_FILES)) {
+                    match FileInfo::read(&path) {
+                        Ok(info) => {
+                            self.status_message = format!(
+                                " Inspecting {:?} (Esc to close)",
+                                path.file_name().unwrap_or_default()
+                            );
+                            self.panel = Some(Panel::Info(info));
+                        }
+                        Err(e) => {
+                            self.panel = None;
+                            self.status_message = format!(" Error: {}", e);
+                        }
+                    }
                 }
             }
-            KeyCode::Char('d') => {
-                if let Some(input_path) = rfd::FileDialog::new()
-                    .add_filter("Zstd compressed", &["zst"])
-                    .pick_file()
-                {
-                    let output_path = input_path.with_extension("");
+            Action::Decompress if !self.is_compressing => {
+                if let Some(input_path) = self.picker.pick_file(Some(input::ZSTD_FILES)) {
+                    let output_path = self.config.naming().decompressed(&input_path);
+                    if let Err(e) = create_parent(&output_path) {
+                        self.status_message = format!(" Error: {}", e);
+                        return;
+                    }
 
                     let (tx, rx) = std::sync::mpsc::channel();
                     self.receiver = Some(rx);
@@ -166,50 +958,38 @@ impl App {
                         input_path.file_name().unwrap_or_default()
                     );
 
+                    self.running_job = Some(JobStart::new(
+                        JobKind::Decompress,
+                        input_path.clone(),
+                        Some(output_path.clone()),
+                        None,
+                    ));
                     crate::start_decompression(
                         input_path.to_string_lossy().to_string(),
                         output_path.to_string_lossy().to_string(),
                         tx,
+                        self.watched_options(),
                     );
                 }
             }
-            KeyCode::Char('o') => {
-                // 1. Open the native OS file dialogue
-                if let Some(input_path) = rfd::FileDialog::new().pick_file() {
-                    // 2. The user picked a file, so read it
-                    // 2. Automatically create the output path (e.g., "document.pdf" -> "document.pdf.zst")
-                    let mut output_path = input_path.clone();
-                    let mut new_extension =
-                        output_path.extension().unwrap_or_default().to_os_string();
-                    new_extension.push(".zst");
-                    output_path.set_extension(new_extension);
-
-                    // 3. Set up the communication channel for the background thread
-                    let (tx, rx) = std::sync::mpsc::channel();
-                    self.receiver = Some(rx);
-                    self.is_compressing = true;
-                    self.progress = 0.0;
-                    self.compression_finished_at = None;
-
-                    // Let the user know we're starting
-                    self.status_message = format!(
-                        " Compressing {:?} [{}]",
-                        input_path.file_name().unwrap_or_default(),
-                        self.compression_level.label(),
-                    );
-
-                    crate::start_compression(
-                        input_path.to_string_lossy().to_string(),
-                        output_path.to_string_lossy().to_string(),
-                        tx,
-                        self.compression_level,
-                    );
+            Action::OpenFile if !self.is_compressing => {
+                // Open the native OS file dialogue, then estimate before committing to the job
+                if let Some(input_path) = self.picker.pick_file(None) {
+                    self.pending = Some(PendingJob {
+                        input_path,
+                        estimate: None,
+                    });
+                    self.start_estimate();
                 } else {
                     // TODO handle error gracefully
-                    self.status_message = format!(
-                        "Not Compressing ",
-                        // input_path.file_name().unwrap_or_default()
-                    );
+                    self.status_message = "Not Compressing ".to_string();
+                }
+            }
+            Action::OpenFolder if !self.is_compressing => {
+                if let Some(dir) = self.picker.pick_folder() {
+                    self.pending = None;
+                    self.estimate_receiver = None;
+                    self.start_batch_job(dir);
                 }
             }
             _ => {}
@@ -220,3 +1000,146 @@ impl App {
         self.exit = true;
     }
 }
+
+/// The human-readable report shown after a job, shared by the TUI and the CLI.
+/// For decompression `original_size` is the compressed input and `compressed_size`
+/// the decoded output, matching how `start_decompression` fills in `Finished`.
+pub(crate) fn result_summary(
+    is_decompressing: bool,
+    original_size: u64,
+    compressed_size: u64,
+    output_path: &str,
+    source_removed: bool,
+) -> String {
+    let mut summary = if is_decompressing {
+        format!(
+            "\nDecompression successful!\nSaved to: {}\nCompressed: {} bytes\nDecompressed: {} bytes\n",
+            output_path, original_size, compressed_size
+        )
+    } else {
+        let ratio = if original_size > 0 {
+            (compressed_size as f64 / original_size as f64) * 100.0
+        } else {
+            0.0
+        };
+        format!(
+            "\nCompression successful!\nSaved to: {}\nOriginal: {} bytes\nCompressed: {} bytes ({:.2}% of original)\n",
+            output_path, original_size, compressed_size, ratio
+        )
+    };
+    if source_removed {
+        summary.push_str("Source file removed\n");
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::{FakeClock, ScriptedEvents, ScriptedPicker};
+
+    #[test]
+    fn scripted_session_follows_the_state_machine() {
+        let clock = FakeClock::default();
+        let events = ScriptedEvents::new(clock.clone());
+        let picked = PathBuf::from("/nonexistent/report.pdf");
+        // Jobs end up in this history, never the user's
+        let dir = std::env::temp_dir().join("freya_test_session");
+        std::fs::remove_dir_all(&dir).ok();
+        let history = History::new(dir.join("history.jsonl"));
+        let mut app = App::default()
+            .with_history(history.clone())
+            .with_events(events.clone())
+            .with_picker(ScriptedPicker::new([Some(picked.clone()), None]))
+            .with_clock(clock.clone());
+
+        // Pick a file, change the level, then think better of it
+        events.keys("o").key(KeyCode::Down).key(KeyCode::Esc);
+        app.handle_events().unwrap();
+        assert_eq!(app.pending.as_ref().unwrap().input_path, picked);
+        app.handle_events().unwrap();
+        assert_eq!(app.compression_level, CompressionLevel::Best);
+        app.handle_events().unwrap();
+        assert!(app.pending.is_none());
+        assert_eq!(app.status_message, " Compression cancelled");
+        // A cancelled dialog leaves nothing pending
+        events.keys("o");
+        app.handle_events().unwrap();
+        assert!(app.pending.is_none());
+
+        // Level and source removal are locked while a job runs
+        let (tx, rx) = mpsc::channel();
+        app.receiver = Some(rx);
+        app.is_compressing = true;
+        events.key(KeyCode::Up).keys("r");
+        app.handle_events().unwrap();
+        app.handle_events().unwrap();
+        assert_eq!(app.compression_level, CompressionLevel::Best);
+        assert!(!app.job_options.remove_source);
+
+        // Progress comes from the counter the job shares
+        let mut reporter = crate::progress::Reporter::new(&tx, 200, ProgressMode::Counter).unwrap();
+        reporter.report(50).unwrap();
+        app.handle_events().unwrap();
+        assert_eq!(app.progress, 0.25);
+        assert!(app.progress_counter.is_some());
+
+        tx.send(CompressMessage::Finished {
+            original_size: 100,
+            compressed_size: 10,
+            output_path: "/nonexistent/report.pdf.zst".to_string(),
+            source_removed: false,
+        })
+        .unwrap();
+        app.handle_events().unwrap();
+        assert!(!app.is_compressing);
+        assert_eq!(app.status_message, " Compression complete!");
+        assert!(app.progress_counter.is_none());
+        let finished_at = app.compression_finished_at.unwrap();
+
+        // Every idle turn waits 50 ms, so the 2 s delay takes 40 of them
+        let mut turns = 0;
+        while !app.should_exit() && turns < 100 {
+            app.handle_events().unwrap();
+            turns += 1;
+        }
+        assert_eq!(turns, 40);
+        assert_eq!(clock.now() - finished_at, AUTO_EXIT_DELAY);
+        assert!(events.is_empty());
+
+        // Without auto-exit the app stays open
+        let mut app = App::new(Config {
+            auto_exit: false,
+            ..Config::default()
+        })
+        .with_history(history.clone())
+        .with_events(events.clone())
+        .with_clock(clock.clone());
+        app.compression_finished_at = Some(clock.now());
+        for _ in 0..100 {
+            app.handle_events().unwrap();
+        }
+        assert!(!app.should_exit());
+
+        // A stop only counts once the job confirms it
+        let (tx, rx) = mpsc::channel();
+        app.receiver = Some(rx);
+        app.is_compressing = true;
+        app.running_job = Some(JobStart::new(JobKind::Compress, picked.clone(), None, None));
+        let mut reporter = crate::progress::Reporter::new(&tx, 200, ProgressMode::Counter).unwrap();
+        events.keys("x");
+        app.handle_events().unwrap();
+        assert!(app.stopping && app.is_compressing);
+        assert!(app.last_result.is_none());
+        let e = reporter.report(100).unwrap_err();
+        tx.send(CompressMessage::Error(e.to_string())).unwrap();
+        app.handle_events().unwrap();
+        assert!(!app.stopping && !app.is_compressing);
+        assert_eq!(app.status_message, " Job stopped");
+        assert!(app.last_result.is_some());
+        let recorded = history.load().unwrap();
+        assert_eq!(recorded.last().unwrap().input, picked);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
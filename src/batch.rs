@@ -0,0 +1,385 @@
+//! Compresses every file under a folder, one after another, with a policy for
+//! content that is already compressed (media, archives...).
+
+use crate::bench::load_sample;
+use crate::estimate::POOR_RATIO;
+use crate::output::{OutputNaming, create_parent};
+use crate::progress::{ProgressMode, Reporter};
+use crate::{Algorithm, CompressMessage, CompressionLevel, JobOptions, human_bytes};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+/// How much of each file to look at when deciding whether it's already compressed.
+const DETECT_SAMPLE_SIZE: u64 = 256 * 1024;
+
+/// What to do with files that turn out to be already compressed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CompressedPolicy {
+    /// Compress them anyway at the selected level.
+    Compress,
+    /// Leave them alone and list them in the report.
+    #[default]
+    Skip,
+    /// Wrap them in raw zstd blocks (`Algorithm::Store`) so the whole folder ends up as `.zst`.
+    Store,
+}
+
+impl CompressedPolicy {
+    pub fn label(self) -> &'static str {
+        match self {
+            CompressedPolicy::Compress => "compress",
+            CompressedPolicy::Skip => "skip",
+            CompressedPolicy::Store => "store",
+        }
+    }
+}
+
+impl std::str::FromStr for CompressedPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "compress" => Ok(CompressedPolicy::Compress),
+            "skip" => Ok(CompressedPolicy::Skip),
+            "store" => Ok(CompressedPolicy::Store),
+            _ => Err(format!(
+                "unknown policy '{}' (expected compress, skip or store)",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchEntry {
+    pub path: PathBuf,
+    pub output_path: PathBuf,
+    pub original_size: u64,
+    pub compressed_size: u64,
+    pub source_removed: bool,
+    pub duration: Duration,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BatchReport {
+    pub compressed: Vec<BatchEntry>,
+    pub stored: Vec<BatchEntry>,
+    /// Files left untouched, with the reason.
+    pub skipped: Vec<(PathBuf, String)>,
+    pub failed: Vec<(PathBuf, String)>,
+}
+
+impl BatchReport {
+    pub fn counts(&self) -> String {
+        format!(
+            "{} compressed, {} stored, {} skipped, {} failed",
+            self.compressed.len(),
+            self.stored.len(),
+            self.skipped.len(),
+            self.failed.len()
+        )
+    }
+
+    /// The report printed after the batch, listing everything that wasn't compressed.
+    pub fn summary(&self) -> String {
+        let written = self.compressed.iter().chain(&self.stored);
+        let original: u64 = written.clone().map(|e| e.original_size).sum();
+        let compressed: u64 = written.map(|e| e.compressed_size).sum();
+        let ratio = if original > 0 {
+            compressed as f64 / original as f64 * 100.0
+        } else {
+            0.0
+        };
+
+        let mut summary = format!(
+            "\nBatch complete: {}\nOriginal: {}\nCompressed: {} ({:.2}% of original)\n",
+            self.counts(),
+            human_bytes(original),
+            human_bytes(compressed),
+            ratio
+        );
+        if !self.stored.is_empty() {
+            summary.push_str("Stored without compression:\n");
+            for entry in &self.stored {
+                summary.push_str(&format!("  {}\n", entry.path.display()));
+            }
+        }
+        if !self.skipped.is_empty() {
+            summary.push_str("Skipped:\n");
+            for (path, reason) in &self.skipped {
+                summary.push_str(&format!("  {} ({})\n", path.display(), reason));
+            }
+        }
+        if !self.failed.is_empty() {
+            summary.push_str("Failed:\n");
+            for (path, error) in &self.failed {
+                summary.push_str(&format!("  {}: {}\n", path.display(), error));
+            }
+        }
+        summary
+    }
+}
+
+/// Every regular file under `dir`, sorted. Symlinks are not followed.
+pub fn collect_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        for entry in std::fs::read_dir(&dir)? {
+            let entry = entry?;
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                pending.push(entry.path());
+            } else if file_type.is_file() {
+                files.push(entry.path());
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Why a file looks already compressed, if it does: magic bytes first, then how
+/// little a sample spread across the file shrinks at the fastest level. Byte
+/// frequencies alone would miss structure that compresses well.
+pub fn detect_compressed(path: &Path) -> io::Result<Option<String>> {
+    let (sample, _) = load_sample(&path.to_string_lossy(), DETECT_SAMPLE_SIZE)?;
+    if let Some(format) = crate::sniff::sniff(&sample) {
+        return Ok(Some(format.name().to_string()));
+    }
+    if sample.is_empty() {
+        return Ok(None);
+    }
+    let compressed = zstd::bulk::compress(&sample, CompressionLevel::Fast.zstd_level())?;
+    let ratio = compressed.len() as f64 / sample.len() as f64 * 100.0;
+    Ok((ratio >= POOR_RATIO).then(|| format!("a sample shrinks only to {:.1}%", ratio)))
+}
+
+/// Compresses every file under `dir`, next to itself or mirrored into the output
+/// dir of `naming`. Progress covers the whole folder; the batch ends with a single
+/// `CompressMessage::BatchFinished`.
+pub fn start_batch(
+    dir: PathBuf,
+    naming: OutputNaming,
+    tx: mpsc::Sender<CompressMessage>,
+    level: CompressionLevel,
+    options: JobOptions,
+    policy: CompressedPolicy,
+) {
+    std::thread::spawn(move || {
+        let files = match collect_files(&dir) {
+            Ok(files) => files,
+            Err(e) => {
+                let _ = tx.send(CompressMessage::Error(e.to_string()));
+                return;
+            }
+        };
+        let sizes: Vec<u64> = files
+            .iter()
+            .map(|path| path.metadata().map_or(0, |m| m.len()))
+            .collect();
+        let total_bytes = sizes.iter().sum();
+        let Ok(mut reporter) = Reporter::new(&tx, total_bytes, options.progress) else {
+            return;
+        };
+
+        let mut report = BatchReport::default();
+        let mut done = 0;
+        for (path, size) in files.into_iter().zip(sizes) {
+            let subdir = path
+                .parent()
+                .and_then(|parent| parent.strip_prefix(&dir).ok())
+                .unwrap_or(Path::new(""));
+            let output_path = naming.nested(subdir).compressed(&path);
+            let progress = (&mut reporter, done);
+            let outcome = process_file(
+                path,
+                output_path,
+                level,
+                options,
+                policy,
+                progress,
+                &mut report,
+            )
+            .and_then(|()| reporter.report(done + size));
+            // Nobody watches any more: the batch was stopped. Tell whoever
+            // stopped it, in case they wait for the confirmation.
+            if let Err(e) = outcome {
+                let _ = tx.send(CompressMessage::Error(e.to_string()));
+                return;
+            }
+            done += size;
+        }
+        let _ = tx.send(CompressMessage::BatchFinished(report));
+    });
+}
+
+// `progress` is the batch's reporter and the bytes done before this file. Fails
+// only when the batch is stopped; the file's own failures go into `report`.
+#[allow(clippy::too_many_arguments)]
+fn process_file(
+    path: PathBuf,
+    output_path: Result<PathBuf, String>,
+    level: CompressionLevel,
+    mut options: JobOptions,
+    policy: CompressedPolicy,
+    (reporter, done): (&mut Reporter, u64),
+    report: &mut BatchReport,
+) -> io::Result<()> {
+    // Our own output from an earlier run; compressing it again would give .zst.zst
+    if path.extension().is_some_and(|ext| ext == "zst") {
+        report.skipped.push((path, "already a .zst".to_string()));
+        return Ok(());
+    }
+
+    if policy != CompressedPolicy::Compress {
+        match detect_compressed(&path) {
+            Ok(None) => {}
+            Ok(Some(reason)) if policy == CompressedPolicy::Skip => {
+                report.skipped.push((path, reason));
+                return Ok(());
+            }
+            Ok(Some(_)) => options.algorithm = Algorithm::Store,
+            Err(e) => {
+                report.failed.push((path, e.to_string()));
+                return Ok(());
+            }
+        }
+    }
+
+    let output_path = match output_path {
+        Ok(output_path) => output_path,
+        Err(e) => {
+            report.failed.push((path, e));
+            return Ok(());
+        }
+    };
+    if let Err(e) = create_parent(&output_path) {
+        report.failed.push((path, e.to_string()));
+        return Ok(());
+    }
+
+    // Reuse the single-file job and forward its progress, offset by what's already done
+    let started = Instant::now();
+    options.progress = ProgressMode::Messages;
+    let (file_tx, file_rx) = mpsc::channel();
+    crate::start_compression(
+        path.to_string_lossy().to_string(),
+        output_path.to_string_lossy().to_string(),
+        file_tx,
+        level,
+        options,
+    );
+    for msg in file_rx {
+        match msg {
+            CompressMessage::Progress {
+                bytes_processed, ..
+            } => {
+                // Dropping `file_rx` on the way out stops the file's job too
+                reporter.report(done + bytes_processed)?;
+            }
+            CompressMessage::Finished {
+                original_size,
+                compressed_size,
+                source_removed,
+                ..
+            } => {
+                let entry = BatchEntry {
+                    path,
+                    output_path,
+                    original_size,
+                    compressed_size,
+                    source_removed,
+                    duration: started.elapsed(),
+                };
+                match options.algorithm {
+                    Algorithm::Store => report.stored.push(entry),
+                    Algorithm::Zstd => report.compressed.push(entry),
+                }
+                return Ok(());
+            }
+            CompressMessage::Error(e) => {
+                report.failed.push((path, e));
+                return Ok(());
+            }
+            CompressMessage::Counter(_) | CompressMessage::BatchFinished(_) => {
+                unreachable!("single-file jobs in message mode")
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A PNG header is enough to trip the detector, and so is noise; plain text
+    // and bytes that only look random by their frequencies get compressed.
+    #[test]
+    fn batch_skips_or_stores_compressed_files() {
+        let dir = std::env::temp_dir().join("freya_test_batch");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("nested")).unwrap();
+        std::fs::write(dir.join("notes.txt"), b"compress me please\n".repeat(500)).unwrap();
+        let mut png = b"\x89PNG\r\n\x1A\n".to_vec();
+        png.extend(std::iter::repeat_n(0u8, 4096));
+        std::fs::write(dir.join("nested").join("image.png"), &png).unwrap();
+        let cycle: Vec<u8> = (0..=255u8).cycle().take(64 * 1024).collect();
+        std::fs::write(dir.join("cycle.bin"), &cycle).unwrap();
+        let mut state = 0x9E37_79B9_7F4A_7C15u64;
+        let noise: Vec<u8> = (0..64 * 1024)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect();
+        std::fs::write(dir.join("noise.bin"), &noise).unwrap();
+
+        let run = |policy| {
+            let (tx, rx) = mpsc::channel();
+            start_batch(
+                dir.clone(),
+                OutputNaming::default(),
+                tx,
+                CompressionLevel::Fast,
+                JobOptions::default(),
+                policy,
+            );
+            rx.iter()
+                .find_map(|msg| match msg {
+                    CompressMessage::BatchFinished(report) => Some(report),
+                    _ => None,
+                })
+                .unwrap()
+        };
+
+        let report = run(CompressedPolicy::Skip);
+        assert_eq!(report.compressed.len(), 2);
+        let skipped: Vec<_> = report.skipped.iter().map(|(path, _)| path).collect();
+        assert_eq!(
+            skipped,
+            [
+                &dir.join("nested").join("image.png"),
+                &dir.join("noise.bin")
+            ]
+        );
+        assert!(report.summary().contains("image.png (PNG)"));
+
+        // Second run: the .zst files from the first run are skipped, the PNG
+        // and the noise get stored
+        let report = run(CompressedPolicy::Store);
+        assert_eq!(report.stored.len(), 2);
+        assert_eq!(report.compressed.len(), 2);
+        assert_eq!(report.skipped.len(), 2);
+        let stored = std::fs::read(dir.join("nested").join("image.png.zst")).unwrap();
+        assert_eq!(zstd::decode_all(&stored[..]).unwrap(), png);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,210 @@
+//! Runs a file (or a sample of it) through several compression levels in memory
+//! so the user can compare ratio and speed before picking one.
+
+use crate::CompressionLevel;
+use crate::progress::stopped;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::sync::mpsc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// Files larger than this are sampled instead of benchmarked whole.
+pub const DEFAULT_SAMPLE_SIZE: u64 = 16 * 1024 * 1024;
+// Samples are taken as this many evenly spaced chunks, so the result reflects
+// the whole file rather than just its header.
+const SAMPLE_CHUNKS: u64 = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LevelResult {
+    pub level: CompressionLevel,
+    pub input_size: u64,
+    pub compressed_size: u64,
+    pub compress_time: Duration,
+    pub decompress_time: Duration,
+}
+
+impl LevelResult {
+    /// Compressed size as a percentage of the input.
+    pub fn ratio(&self) -> f64 {
+        if self.input_size == 0 {
+            return 0.0;
+        }
+        self.compressed_size as f64 / self.input_size as f64 * 100.0
+    }
+
+    /// Input bytes per second while compressing.
+    pub fn compress_speed(&self) -> f64 {
+        self.input_size as f64 / self.compress_time.as_secs_f64().max(f64::EPSILON)
+    }
+
+    /// Output bytes per second while decompressing.
+    pub fn decompress_speed(&self) -> f64 {
+        self.input_size as f64 / self.decompress_time.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+pub enum BenchMessage {
+    /// Sent before each level starts, so the UI can say what's running.
+    Running(CompressionLevel),
+    Result(LevelResult),
+    Finished {
+        sample_size: u64,
+        file_size: u64,
+    },
+    Error(String),
+}
+
+/// Reads the whole file if it fits in `max_len`, otherwise evenly spaced chunks
+/// adding up to `max_len`.
+pub fn load_sample(path: &str, max_len: u64) -> io::Result<(Vec<u8>, u64)> {
+    let mut file = File::open(path)?;
+    let file_size = file.metadata()?.len();
+    if file_size <= max_len {
+        let mut data = Vec::with_capacity(file_size as usize);
+        file.read_to_end(&mut data)?;
+        return Ok((data, file_size));
+    }
+
+    let chunk_len = max_len / SAMPLE_CHUNKS;
+    let stride = file_size / SAMPLE_CHUNKS;
+    let mut data = Vec::with_capacity(max_len as usize);
+    for chunk in 0..SAMPLE_CHUNKS {
+        file.seek(SeekFrom::Start(chunk * stride))?;
+        (&mut file).take(chunk_len).read_to_end(&mut data)?;
+    }
+    Ok((data, file_size))
+}
+
+/// Compresses and decompresses `data` once at `level`, timing both directions.
+pub fn benchmark_level(data: &[u8], level: CompressionLevel) -> io::Result<LevelResult> {
+    let started = Instant::now();
+    let compressed = zstd::bulk::compress(data, level.zstd_level())?;
+    let compress_time = started.elapsed();
+
+    let started = Instant::now();
+    let decompressed = zstd::bulk::decompress(&compressed, data.len())?;
+    let decompress_time = started.elapsed();
+
+    if decompressed != data {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} level did not round-trip", level.label()),
+        ));
+    }
+    Ok(LevelResult {
+        level,
+        input_size: data.len() as u64,
+        compressed_size: compressed.len() as u64,
+        compress_time,
+        decompress_time,
+    })
+}
+
+/// Benchmarks `levels` on a background thread, reporting each result as it lands.
+/// Dropping the receiver stops the thread after the level it is on, and the
+/// handle then yields an `Interrupted` error.
+pub fn start_benchmark(
+    input_path: String,
+    levels: Vec<CompressionLevel>,
+    sample_size: u64,
+    tx: mpsc::Sender<BenchMessage>,
+) -> JoinHandle<io::Result<(u64, u64)>> {
+    std::thread::spawn(move || {
+        let run = || -> io::Result<(u64, u64)> {
+            let (data, file_size) = load_sample(&input_path, sample_size)?;
+            for level in levels {
+                tx.send(BenchMessage::Running(level))
+                    .map_err(|_| stopped())?;
+                let result = benchmark_level(&data, level)?;
+                tx.send(BenchMessage::Result(result))
+                    .map_err(|_| stopped())?;
+            }
+            Ok((data.len() as u64, file_size))
+        };
+
+        let result = run();
+        match &result {
+            &Ok((sample_size, file_size)) => {
+                let _ = tx.send(BenchMessage::Finished {
+                    sample_size,
+                    file_size,
+                });
+            }
+            Err(e) => {
+                let _ = tx.send(BenchMessage::Error(e.to_string()));
+            }
+        }
+        result
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A file smaller than the sample is benchmarked whole, once per level.
+    #[test]
+    fn benchmarks_every_level_asked_for() {
+        let dir = std::env::temp_dir().join("freya_test_bench");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("sample.txt");
+        let data = b"Freya benchmarks each level on the same sample.\n".repeat(200);
+        std::fs::write(&path, &data).unwrap();
+
+        let levels = vec![CompressionLevel::Fast, CompressionLevel::Best];
+        let (tx, rx) = mpsc::channel();
+        start_benchmark(
+            path.to_string_lossy().to_string(),
+            levels.clone(),
+            DEFAULT_SAMPLE_SIZE,
+            tx,
+        );
+        let mut results = Vec::new();
+        let mut finished = None;
+        for msg in rx {
+            match msg {
+                BenchMessage::Running(_) => {}
+                BenchMessage::Result(result) => results.push(result),
+                BenchMessage::Finished {
+                    sample_size,
+                    file_size,
+                } => finished = Some((sample_size, file_size)),
+                BenchMessage::Error(e) => panic!("benchmark failed: {}", e),
+            }
+        }
+        let size = data.len() as u64;
+        assert_eq!(finished, Some((size, size)));
+        assert_eq!(results.iter().map(|r| r.level).collect::<Vec<_>>(), levels);
+        for result in &results {
+            assert_eq!(result.input_size, size);
+            assert!(result.compressed_size < size / 10, "{:?}", result);
+        }
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    // Cancelling the view drops the receiver, which must end the thread instead
+    // of leaving it to grind through the remaining levels. Sends never block, so
+    // the receiver goes first to keep the thread from racing ahead.
+    #[test]
+    fn dropping_the_receiver_stops_the_benchmark() {
+        let dir = std::env::temp_dir().join("freya_test_bench_cancel");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("sample.txt");
+        std::fs::write(&path, b"Cancelled before the first level.\n".repeat(200)).unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        drop(rx);
+        let handle = start_benchmark(
+            path.to_string_lossy().to_string(),
+            vec![CompressionLevel::Fast; 3],
+            DEFAULT_SAMPLE_SIZE,
+            tx,
+        );
+
+        let err = handle.join().unwrap().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Interrupted);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,715 @@
+use crate::batch::{self, CompressedPolicy};
+use crate::config::Config;
+use crate::history::{self, History, HistoryEntry, JobKind, JobStart, SortKey};
+use crate::info::FileInfo;
+use crate::output::{OutputNaming, create_parent, validate_template};
+use crate::report::{self, Event, JobResult, Status};
+use crate::seekable::{DEFAULT_FRAME_SIZE, MAX_FRAME_SIZE, SeekableReader};
+use crate::settings::parse_output_dir;
+use crate::theme::Theme;
+use crate::{
+    Algorithm, CompressMessage, CompressionLevel, JobOptions, OverwritePolicy, ReadMode,
+    app::result_summary, human_bytes, parse_buffer_size, parse_size,
+};
+use clap::{Args, Parser, Subcommand};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::mpsc;
+
+/// Command-line interface. With no subcommand Freya starts the TUI.
+/// Options left out fall back to the config file and `FREYA_*` environment variables.
+#[derive(Debug, Parser)]
+#[command(
+    name = "freya",
+    version,
+    about = "Lossless compression for the terminal age",
+    args_conflicts_with_subcommands = true
+)]
+pub struct Cli {
+    /// TUI only: print the last job's result as JSON on exit instead of a summary
+    #[arg(long)]
+    pub json: bool,
+    #[command(flatten)]
+    pub settings: SettingArgs,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// The TUI's settings, over the config file and environment for this run only;
+/// the settings screen still saves to the file without them.
+#[derive(Debug, Args)]
+pub struct SettingArgs {
+    /// TUI only: fast, normal or best
+    #[arg(short, long)]
+    level: Option<CompressionLevel>,
+    /// TUI only: zstd or store
+    #[arg(short, long)]
+    algorithm: Option<Algorithm>,
+    /// TUI only: threads for multi-frame files (0 = one per core)
+    #[arg(short = 'T', long)]
+    threads: Option<usize>,
+    /// TUI only: write outputs here instead of next to the input
+    #[arg(short = 'O', long)]
+    output_dir: Option<PathBuf>,
+    /// TUI only: always, or never to fail instead of replacing an existing output
+    #[arg(long)]
+    overwrite: Option<OverwritePolicy>,
+    /// TUI only: verify outputs after compressing
+    #[arg(long, overrides_with = "no_verify")]
+    verify: bool,
+    /// TUI only: don't verify, even if the config says so
+    #[arg(long, overrides_with = "verify")]
+    no_verify: bool,
+    /// TUI only: a built-in theme or one from the config's [themes]
+    #[arg(long)]
+    theme: Option<String>,
+}
+
+impl SettingArgs {
+    /// Overrides `config` with the flags that were given.
+    pub fn apply(&self, config: &mut Config) -> Result<(), String> {
+        if let Some(theme) = &self.theme {
+            Theme::named(theme, &config.themes)?;
+            config.theme = Some(theme.clone());
+        }
+        if let Some(dir) = &self.output_dir {
+            config.output_dir = parse_output_dir(&dir.to_string_lossy())?;
+        }
+        config.level = self.level.unwrap_or(config.level);
+        config.algorithm = self.algorithm.unwrap_or(config.algorithm);
+        config.threads = self.threads.unwrap_or(config.threads);
+        config.overwrite = self.overwrite.unwrap_or(config.overwrite);
+        config.verify = self.verify || (config.verify && !self.no_verify);
+        Ok(())
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Compress a file to `<file>.zst`, or every file in a folder
+    Compress(CompressArgs),
+    /// Decompress a `.zst` file next to the original
+    Decompress(DecompressArgs),
+    /// Show frames, sizes and settings of a compressed file without decompressing it
+    Info {
+        file: PathBuf,
+        /// List every frame
+        #[arg(short, long)]
+        verbose: bool,
+    },
+    /// Compare ratio and speed of each level on a file, in memory
+    Bench {
+        file: PathBuf,
+        /// Levels to try: a range like fast..normal or a list like fast,best
+        #[arg(long, value_parser = parse_levels, default_value = "fast..best")]
+        levels: LevelList,
+        /// Benchmark a sample of this many bytes from across larger files
+        #[arg(long, value_parser = parse_size, default_value = "16M")]
+        sample: u64,
+    },
+    /// Copy a byte range out of a seekable file without decoding the rest
+    Extract {
+        file: PathBuf,
+        /// START:LEN in decompressed bytes, e.g. 1G:64M
+        #[arg(long, value_parser = parse_range)]
+        range: ByteRange,
+        /// Write here instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// List past jobs from the history file
+    History {
+        /// Print a JSON array instead of a table
+        #[arg(long)]
+        json: bool,
+        /// date, ratio, size or duration
+        #[arg(long, default_value = "date")]
+        sort: SortKey,
+        /// Only jobs whose paths, kind, level or outcome contain this text
+        #[arg(long)]
+        filter: Option<String>,
+        /// Show at most this many jobs
+        #[arg(short = 'n', long)]
+        limit: Option<usize>,
+    },
+}
+
+#[derive(Debug, Args)]
+pub struct CompressArgs {
+    file: PathBuf,
+    /// fast, normal or best
+    #[arg(short, long)]
+    level: Option<CompressionLevel>,
+    /// zstd, or store to wrap the input in a zstd frame without compressing it
+    #[arg(short, long)]
+    algorithm: Option<Algorithm>,
+    /// Folders only: skip, store or compress files that are already compressed
+    #[arg(long)]
+    on_compressed: Option<CompressedPolicy>,
+    /// Decode the output again and compare it with the input before finishing
+    #[arg(long, overrides_with = "no_verify")]
+    verify: bool,
+    /// Don't verify, even if the config says so
+    #[arg(long, overrides_with = "verify")]
+    no_verify: bool,
+    /// Store a content checksum in each frame
+    #[arg(long, overrides_with = "no_checksum")]
+    checksum: bool,
+    /// Don't store checksums, even if the config says so
+    #[arg(long, overrides_with = "checksum")]
+    no_checksum: bool,
+    /// Long-distance matching (128 MiB window) for big files with far-apart repeats
+    #[arg(long, overrides_with = "no_long")]
+    long: bool,
+    /// No long-distance matching, even if the config says so
+    #[arg(long, overrides_with = "long")]
+    no_long: bool,
+    /// Write the seekable format (independent frames plus a seek table)
+    #[arg(long)]
+    seekable: bool,
+    /// Input bytes per seekable frame, e.g. 512K or 4M
+    #[arg(long, value_parser = parse_frame_size, requires = "seekable")]
+    frame_size: Option<u32>,
+    /// Output file name template: {name}, {stem}, {ext} and {date}, e.g. "{stem}-{date}.{ext}.zst"
+    #[arg(long, value_parser = parse_template)]
+    name: Option<String>,
+    /// Bytes read and written at a time, e.g. 64K or 4M (default 1M)
+    #[arg(long, value_parser = parse_buffer_size)]
+    buffer_size: Option<usize>,
+    /// How to read the input: auto (mapped from 64M up), buffered or mapped
+    #[arg(long)]
+    read: Option<ReadMode>,
+    #[command(flatten)]
+    source: SourceArgs,
+    #[command(flatten)]
+    output: OutputArgs,
+    #[command(flatten)]
+    report: ReportArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct DecompressArgs {
+    file: PathBuf,
+    /// Threads for multi-frame files (0 = one per core)
+    #[arg(short = 'T', long)]
+    threads: Option<usize>,
+    /// Bytes read and written at a time, e.g. 64K or 4M (default 1M)
+    #[arg(long, value_parser = parse_buffer_size)]
+    buffer_size: Option<usize>,
+    /// Write every zero byte instead of leaving holes in the output
+    #[arg(long)]
+    no_sparse: bool,
+    #[command(flatten)]
+    source: SourceArgs,
+    #[command(flatten)]
+    output: OutputArgs,
+    #[command(flatten)]
+    report: ReportArgs,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LevelList(pub Vec<CompressionLevel>);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteRange {
+    pub start: u64,
+    pub len: u64,
+}
+
+#[derive(Debug, Args)]
+pub struct SourceArgs {
+    /// Remove the input once the output is fully written
+    #[arg(long = "rm", overrides_with = "keep")]
+    remove: bool,
+    /// Keep the input (default)
+    #[arg(long, overrides_with = "remove")]
+    keep: bool,
+}
+
+impl SourceArgs {
+    /// Only one of the two flags survives `overrides_with`; neither means the configured default.
+    fn remove_source(&self, default: bool) -> bool {
+        if self.remove {
+            true
+        } else if self.keep {
+            false
+        } else {
+            default
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct OutputArgs {
+    /// Write outputs here instead of next to the input (created if missing)
+    #[arg(short = 'O', long)]
+    output_dir: Option<PathBuf>,
+    /// always, or never to fail instead of replacing an existing output
+    #[arg(long)]
+    overwrite: Option<OverwritePolicy>,
+}
+
+#[derive(Debug, Clone, Copy, Args)]
+pub struct ReportArgs {
+    /// Print the result as one line of JSON (see "JSON output" in the README)
+    #[arg(long)]
+    json: bool,
+    /// With --json, also print a JSON progress event per update
+    #[arg(long, requires = "json")]
+    progress: bool,
+}
+
+impl OutputArgs {
+    fn naming(&self, config: &Config, template: Option<&String>) -> OutputNaming {
+        OutputNaming {
+            dir: self
+                .output_dir
+                .clone()
+                .or_else(|| config.output_dir.clone()),
+            template: template.cloned().or_else(|| config.output_template.clone()),
+        }
+    }
+}
+
+pub fn run(command: Command, config: &Config) -> ExitCode {
+    match command {
+        Command::Extract {
+            file,
+            range,
+            output,
+        } => match extract(&file, range, output.as_deref()) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                ExitCode::FAILURE
+            }
+        },
+        Command::Info { file, verbose } => match FileInfo::read(&file) {
+            Ok(info) => {
+                println!("{}", info);
+                if verbose {
+                    println!();
+                    for line in info.frame_lines() {
+                        println!("{}", line);
+                    }
+                }
+                ExitCode::SUCCESS
+            }
+            Err(e) => {
+                eprintln!("Error: {}: {}", file.display(), e);
+                ExitCode::FAILURE
+            }
+        },
+        Command::Bench {
+            file,
+            levels,
+            sample,
+        } => match bench(&file, levels.0, sample) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                ExitCode::FAILURE
+            }
+        },
+        Command::History {
+            json,
+            sort,
+            filter,
+            limit,
+        } => match show_history(json, sort, filter.as_deref().unwrap_or(""), limit) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                ExitCode::FAILURE
+            }
+        },
+        Command::Compress(args) => compress(&args, config),
+        Command::Decompress(args) => decompress(&args, config),
+    }
+}
+
+/// Compresses a file or folder headless and prints the same summary the TUI
+/// shows on exit.
+fn compress(args: &CompressArgs, config: &Config) -> ExitCode {
+    let CompressArgs {
+        file,
+        level,
+        algorithm,
+        on_compressed,
+        verify,
+        no_verify,
+        checksum,
+        no_checksum,
+        long,
+        no_long,
+        seekable,
+        frame_size,
+        name,
+        buffer_size,
+        read,
+        source,
+        output,
+        report,
+    } = args;
+    let (tx, rx) = mpsc::channel();
+    let naming = output.naming(config, name.as_ref());
+    let level = level.unwrap_or(config.level);
+    let options = JobOptions {
+        algorithm: algorithm.unwrap_or(config.algorithm),
+        remove_source: source.remove_source(config.remove_source),
+        verify: *verify || (config.verify && !no_verify),
+        seekable_frame_size: seekable.then(|| frame_size.unwrap_or(DEFAULT_FRAME_SIZE)),
+        overwrite: output.overwrite.unwrap_or(config.overwrite),
+        checksum: *checksum || (config.checksum && !no_checksum),
+        long_distance: *long || (config.long_distance && !no_long),
+        buffer_size: buffer_size.unwrap_or(config.buffer_size),
+        read: read.unwrap_or(config.read),
+        ..config.job_options()
+    };
+    // Store writes the input as one frame, so there would be nothing to seek between
+    if options.algorithm == Algorithm::Store && options.seekable_frame_size.is_some() {
+        eprintln!("Error: --seekable and --frame-size need the zstd algorithm, not store");
+        return ExitCode::FAILURE;
+    }
+    let mut job = JobStart::new(JobKind::Compress, file.clone(), None, Some(level));
+    if file.is_dir() {
+        job.output = naming.dir.clone();
+        batch::start_batch(
+            file.clone(),
+            naming,
+            tx,
+            level,
+            options,
+            on_compressed.unwrap_or(config.on_compressed),
+        );
+        return drain(rx, job, *report);
+    }
+    let output_path = match naming.compressed(file) {
+        Ok(output_path) => output_path,
+        Err(e) => return fail(&job, &e, *report),
+    };
+    job.output = Some(output_path.clone());
+    if let Err(e) = create_parent(&output_path) {
+        return fail(&job, &e.to_string(), *report);
+    }
+    crate::start_compression(
+        path_string(file),
+        path_string(&output_path),
+        tx,
+        level,
+        options,
+    );
+    drain(rx, job, *report)
+}
+
+/// Decompresses a file headless and prints the same summary the TUI shows on exit.
+fn decompress(args: &DecompressArgs, config: &Config) -> ExitCode {
+    let DecompressArgs {
+        file,
+        threads,
+        buffer_size,
+        no_sparse,
+        source,
+        output,
+        report,
+    } = args;
+    let (tx, rx) = mpsc::channel();
+    let options = JobOptions {
+        remove_source: source.remove_source(config.remove_source),
+        threads: threads.unwrap_or(config.threads),
+        buffer_size: buffer_size.unwrap_or(config.buffer_size),
+        sparse: config.sparse && !no_sparse,
+        overwrite: output.overwrite.unwrap_or(config.overwrite),
+        ..config.job_options()
+    };
+    let output_path = output.naming(config, None).decompressed(file);
+    let job = JobStart::new(
+        JobKind::Decompress,
+        file.clone(),
+        Some(output_path.clone()),
+        None,
+    );
+    if let Err(e) = create_parent(&output_path) {
+        return fail(&job, &e.to_string(), *report);
+    }
+    crate::start_decompression(path_string(file), path_string(&output_path), tx, options);
+    drain(rx, job, *report)
+}
+
+/// Waits for `job` to end, prints its result and records it in the history.
+fn drain(rx: mpsc::Receiver<CompressMessage>, job: JobStart, report: ReportArgs) -> ExitCode {
+    let is_decompressing = job.kind == JobKind::Decompress;
+    for msg in rx {
+        match msg {
+            CompressMessage::Progress {
+                bytes_processed,
+                total_bytes,
+            } => {
+                if report.progress {
+                    println!(
+                        "{}",
+                        report::to_line(&Event::Progress {
+                            bytes_processed,
+                            total_bytes,
+                        })
+                    );
+                }
+            }
+            CompressMessage::Counter(_) => {}
+            CompressMessage::Finished {
+                original_size,
+                compressed_size,
+                output_path,
+                source_removed,
+            } => {
+                let result = JobResult::finished(
+                    &job,
+                    original_size,
+                    compressed_size,
+                    PathBuf::from(&output_path),
+                    source_removed,
+                );
+                if report.json {
+                    println!("{}", report::to_line(&Event::Result(&result)));
+                } else {
+                    println!(
+                        "{}",
+                        result_summary(
+                            is_decompressing,
+                            original_size,
+                            compressed_size,
+                            &output_path,
+                            source_removed,
+                        )
+                    );
+                }
+                record(&[result.entry]);
+                return ExitCode::SUCCESS;
+            }
+            CompressMessage::BatchFinished(batch) => {
+                let result = JobResult::batch(&job, &batch);
+                if report.json {
+                    println!("{}", report::to_line(&Event::Result(&result)));
+                } else {
+                    println!("{}", batch.summary());
+                }
+                let level = job.level.unwrap_or_default();
+                record(&HistoryEntry::from_batch(&batch, level));
+                return match result.status {
+                    Status::Ok => ExitCode::SUCCESS,
+                    Status::Failed => ExitCode::FAILURE,
+                };
+            }
+            CompressMessage::Error(e) => return fail(&job, &e, report),
+        }
+    }
+    fail(&job, "worker thread exited without a result", report)
+}
+
+// Reports a job that failed, before it started or while running.
+fn fail(job: &JobStart, error: &str, report: ReportArgs) -> ExitCode {
+    eprintln!("Error: {}", error);
+    let result = JobResult::failed(job, error);
+    if report.json {
+        println!("{}", report::to_line(&Event::Result(&result)));
+    }
+    record(&[result.entry]);
+    ExitCode::FAILURE
+}
+
+// Saving the history is best effort: the job itself already succeeded or failed.
+fn record(entries: &[HistoryEntry]) {
+    if let Some(history) = History::open_default()
+        && let Err(e) = history.append(entries)
+    {
+        eprintln!(
+            "Warning: could not save history to {}: {}",
+            history.path().display(),
+            e
+        );
+    }
+}
+
+fn show_history(json: bool, sort: SortKey, filter: &str, limit: Option<usize>) -> io::Result<()> {
+    let entries = match History::open_default() {
+        Some(history) => history.load()?,
+        None => Vec::new(),
+    };
+    let mut selected = history::select(&entries, filter, sort);
+    if let Some(limit) = limit {
+        selected.truncate(limit);
+    }
+
+    if json {
+        let text = serde_json::to_string_pretty(&selected).map_err(io::Error::other)?;
+        println!("{}", text);
+        return Ok(());
+    }
+    println!(
+        "{:<16} {:<10} {:<6} {:>10} {:>8} {:>8}  {:<8} File",
+        "Finished", "Job", "Level", "Input", "Ratio", "Time", "Outcome"
+    );
+    for entry in selected {
+        println!(
+            "{:<16} {:<10} {:<6} {:>10} {:>7.2}% {:>8}  {:<8} {}",
+            history::format_time(entry.finished_at),
+            entry.kind.label(),
+            entry.level.map_or("-", |l| l.label()),
+            human_bytes(entry.input_size),
+            entry.ratio,
+            history::format_duration(entry.duration_ms),
+            if entry.succeeded() { "ok" } else { "failed" },
+            entry.input.display(),
+        );
+        if let Some(error) = &entry.error {
+            println!("{:<17}{}", "", error);
+        }
+    }
+    Ok(())
+}
+
+fn path_string(path: &Path) -> String {
+    path.to_string_lossy().to_string()
+}
+
+fn bench(file: &Path, levels: Vec<CompressionLevel>, sample: u64) -> io::Result<()> {
+    let (data, file_size) = crate::bench::load_sample(&path_string(file), sample)?;
+    if (data.len() as u64) < file_size {
+        println!(
+            "Sampled {} from {}",
+            human_bytes(data.len() as u64),
+            human_bytes(file_size)
+        );
+    }
+    println!(
+        "{:<8} {:>12} {:>8} {:>14} {:>14}",
+        "Level", "Size", "Ratio", "Compress", "Decompress"
+    );
+    for level in levels {
+        let result = crate::bench::benchmark_level(&data, level)?;
+        println!(
+            "{:<8} {:>12} {:>7.2}% {:>12}/s {:>12}/s",
+            level.label(),
+            human_bytes(result.compressed_size),
+            result.ratio(),
+            human_bytes(result.compress_speed() as u64),
+            human_bytes(result.decompress_speed() as u64),
+        );
+    }
+    Ok(())
+}
+
+fn extract(file: &Path, range: ByteRange, output: Option<&Path>) -> io::Result<()> {
+    let mut reader = SeekableReader::new(io::BufReader::new(std::fs::File::open(file)?))?;
+    if range.start > reader.len() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "range starts past the end of the data ({} bytes)",
+                reader.len()
+            ),
+        ));
+    }
+    reader.seek(SeekFrom::Start(range.start))?;
+    let mut slice = reader.take(range.len);
+
+    match output {
+        Some(path) => {
+            let mut out = std::fs::File::create(path)?;
+            io::copy(&mut slice, &mut out)?;
+            out.sync_all()
+        }
+        None => {
+            let mut out = io::stdout().lock();
+            io::copy(&mut slice, &mut out)?;
+            out.flush()
+        }
+    }
+}
+
+fn parse_template(s: &str) -> Result<String, String> {
+    validate_template(s)?;
+    Ok(s.to_string())
+}
+
+fn parse_frame_size(s: &str) -> Result<u32, String> {
+    let size = parse_size(s)?;
+    if size == 0 || size > MAX_FRAME_SIZE as u64 {
+        return Err(format!(
+            "frame size must be between 1 and {}",
+            MAX_FRAME_SIZE
+        ));
+    }
+    Ok(size as u32)
+}
+
+/// "fast..best" (inclusive, either direction) or "fast,best".
+fn parse_levels(s: &str) -> Result<LevelList, String> {
+    if let Some((from, to)) = s.split_once("..") {
+        let position = |level: CompressionLevel| {
+            CompressionLevel::ALL
+                .iter()
+                .position(|&l| l == level)
+                .expect("every level is in ALL")
+        };
+        let (from, to) = (position(from.parse()?), position(to.parse()?));
+        let levels = &CompressionLevel::ALL[from.min(to)..=from.max(to)];
+        return Ok(LevelList(levels.to_vec()));
+    }
+    s.split(',')
+        .map(str::parse)
+        .collect::<Result<_, _>>()
+        .map(LevelList)
+}
+
+fn parse_range(s: &str) -> Result<ByteRange, String> {
+    let (start, len) = s
+        .split_once(':')
+        .ok_or_else(|| format!("expected START:LEN, got '{}'", s))?;
+    Ok(ByteRange {
+        start: parse_size(start)?,
+        len: parse_size(len)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn setting_flags_override_the_config() {
+        let cli = Cli::try_parse_from(["freya", "-l", "best", "--no-verify", "--theme", "light"])
+            .unwrap();
+        let mut config = Config {
+            verify: true,
+            threads: 3,
+            ..Config::default()
+        };
+        cli.settings.apply(&mut config).unwrap();
+        assert_eq!(config.level, CompressionLevel::Best);
+        assert!(!config.verify);
+        assert_eq!(config.theme.as_deref(), Some("light"));
+        assert_eq!(config.threads, 3);
+
+        let cli = Cli::try_parse_from(["freya", "--theme", "plaid"]).unwrap();
+        assert!(cli.settings.apply(&mut config).is_err());
+        // They only make sense for the TUI
+        assert!(Cli::try_parse_from(["freya", "-l", "best", "info", "a.zst"]).is_err());
+    }
+
+    #[test]
+    fn parses_level_ranges_and_lists() {
+        use CompressionLevel::{Best, Fast, Normal};
+        let levels = |s| parse_levels(s).map(|list| list.0);
+        assert_eq!(levels("fast..best"), Ok(vec![Fast, Normal, Best]));
+        assert_eq!(levels("normal"), Ok(vec![Normal]));
+        assert_eq!(levels("normal..normal"), Ok(vec![Normal]));
+        // Either direction gives the same levels, fastest first
+        assert_eq!(levels("best..normal"), Ok(vec![Normal, Best]));
+        assert_eq!(levels("best,fast"), Ok(vec![Best, Fast]));
+        for junk in ["", "turbo", "fast..", "..best", "fast,,best", "fast...best"] {
+            assert!(levels(junk).is_err(), "{:?} parsed", junk);
+        }
+    }
+}
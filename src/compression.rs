@@ -1,51 +1,118 @@
-use crate::{CompressMessage, CompressionLevel};
-use std::io::{BufReader, Read, Write};
-use std::sync::mpsc;
+use crate::progress::Reporter;
+use crate::seekable::{SeekEntry, SeekableWriter, decode_frame};
+use crate::sparse::{self, SparseWriter};
+use crate::store::{self, StoreWriter};
+use crate::{Algorithm, CompressMessage, CompressionLevel, JobOptions, OverwritePolicy, ReadMode};
+use memmap2::Mmap;
+use std::cell::Cell;
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, mpsc};
 use zstd::stream::Decoder;
 
+/// "document.pdf" -> "document.pdf.zst"
+pub fn compressed_path(input_path: &Path) -> PathBuf {
+    let mut output_path = input_path.as_os_str().to_os_string();
+    output_path.push(".zst");
+    PathBuf::from(output_path)
+}
+
 pub fn start_compression(
     input_path: String,
     output_path: String,
     tx: mpsc::Sender<CompressMessage>,
     level: CompressionLevel,
+    options: JobOptions,
 ) {
     std::thread::spawn(move || {
-        let run = || -> std::io::Result<(u64, u64, String)> {
+        // Set once the output is ours to clean up, so a failure to create it
+        // never removes a file that was already there
+        let mut output_created = false;
+        let mut run = || -> std::io::Result<(u64, u64, String, bool)> {
             let mut input_file = std::fs::File::open(&input_path)?;
             let total_bytes = input_file.metadata()?.len();
-            let output_file = std::fs::File::create(&output_path)?;
+            let output_file = create_output(&input_path, &output_path, options.overwrite)?;
+            output_created = true;
+            let mut reporter = Reporter::new(&tx, total_bytes, options.progress)?;
+            let buffer_size = options.buffer_size();
+            let mapped = map_input(&input_file, total_bytes, options);
+            let mapped = mapped.as_deref();
 
-            let mut encoder = zstd::stream::Encoder::new(output_file, level.zstd_level())?;
-            let mut buffer = [0u8; 64 * 1024]; // 64KB buffer
-            let mut bytes_processed: u64 = 0;
-
-            loop {
-                let bytes_read = input_file.read(&mut buffer)?;
-                if bytes_read == 0 {
-                    break;
+            // Seekable output is a series of independent frames plus a seek table;
+            // otherwise the whole file goes through a single streaming encoder.
+            let output_file = match (options.algorithm, options.seekable_frame_size) {
+                (Algorithm::Store, _) if options.read.maps(total_bytes) => {
+                    store::store_file(&mut input_file, output_file, total_bytes, |done| {
+                        reporter.report(done)
+                    })?
                 }
-                encoder.write_all(&buffer[..bytes_read])?;
-                bytes_processed += bytes_read as u64;
-                let _ = tx.send(CompressMessage::Progress {
-                    bytes_processed,
-                    total_bytes,
-                });
-            }
-
-            let output_file = encoder.finish()?;
+                (Algorithm::Store, _) => {
+                    let mut writer = StoreWriter::new(output_file, Some(total_bytes))?;
+                    copy_with_progress(&mut input_file, &mut writer, buffer_size, &mut reporter)?;
+                    writer.finish()?
+                }
+                (Algorithm::Zstd, Some(frame_size)) => {
+                    let mut writer =
+                        SeekableWriter::new(output_file, level.zstd_level(), frame_size)?;
+                    writer.include_checksum(options.checksum);
+                    writer.long_distance_matching(options.long_distance);
+                    feed(
+                        &mut input_file,
+                        mapped,
+                        &mut writer,
+                        buffer_size,
+                        &mut reporter,
+                    )?;
+                    writer.finish()?
+                }
+                (Algorithm::Zstd, None) => {
+                    let mut encoder = zstd::stream::Encoder::new(output_file, level.zstd_level())?;
+                    // Records the size in the frame header so `freya info` can show it
+                    encoder.set_pledged_src_size(Some(total_bytes))?;
+                    encoder.include_checksum(options.checksum)?;
+                    encoder.long_distance_matching(options.long_distance)?;
+                    feed(
+                        &mut input_file,
+                        mapped,
+                        &mut encoder,
+                        buffer_size,
+                        &mut reporter,
+                    )?;
+                    encoder.finish()?
+                }
+            };
+            output_file.sync_all()?;
             let compressed_size = output_file.metadata()?.len();
-            Ok((total_bytes, compressed_size, output_path))
+
+            if options.verify {
+                verify_output(&input_path, &output_path, &mut reporter)?;
+            }
+            // A stop during the last steps still has to keep the source
+            reporter.confirm()?;
+            let source_removed = remove_source_if_requested(&input_path, options)?;
+            Ok((
+                total_bytes,
+                compressed_size,
+                output_path.clone(),
+                source_removed,
+            ))
         };
 
         match run() {
-            Ok((original_size, compressed_size, path)) => {
+            Ok((original_size, compressed_size, path, source_removed)) => {
                 let _ = tx.send(CompressMessage::Finished {
                     original_size,
                     compressed_size,
                     output_path: path,
+                    source_removed,
                 });
             }
             Err(e) => {
+                if output_created {
+                    remove_partial_output(&output_path);
+                }
                 let _ = tx.send(CompressMessage::Error(e.to_string()));
             }
         }
@@ -54,61 +121,474 @@ pub fn start_compression(
 
 // reads a .zst file and writes the original bytes back out.
 // Decoder::new() will reject non-zstd input, so we don't need separate validation.
+// `options.verify` is ignored here: the decoder already checks frame checksums when present.
 pub fn start_decompression(
     input_path: String,
     output_path: String,
     tx: mpsc::Sender<CompressMessage>,
+    options: JobOptions,
 ) {
     std::thread::spawn(move || {
-        let run = || -> std::io::Result<(u64, u64, String)> {
+        let mut output_created = false;
+        let mut run = || -> std::io::Result<(u64, u64, String, bool)> {
             let input_file = std::fs::File::open(&input_path)?;
             let compressed_size = input_file.metadata()?.len();
+            let threads = options.worker_threads();
 
-            // BufReader here because Decoder does many small reads internally
-            let mut decoder = Decoder::new(BufReader::new(input_file))?;
-
-            let mut output_file = std::fs::File::create(&output_path)?;
-            let mut buffer = [0u8; 64 * 1024];
-            let mut bytes_processed: u64 = 0;
+            // Files made of several independent frames with known sizes (our
+            // seekable output, pzstd, zstd -T with content sizes...) are split across
+            // threads. Everything else goes through a single streaming decoder.
+            // The scan reads every frame header, so it goes through a buffer.
+            let frames = if threads > 1 {
+                crate::frames::independent_frames(BufReader::new(&input_file))?
+                    .filter(|f| f.len() > 1)
+            } else {
+                None
+            };
 
-            loop {
-                let bytes_read = decoder.read(&mut buffer)?;
-                if bytes_read == 0 {
-                    break;
+            let mut output_file = create_output(&input_path, &output_path, options.overwrite)?;
+            output_created = true;
+            let mut reporter = Reporter::new(&tx, compressed_size, options.progress)?;
+            let bytes_processed = match frames {
+                Some(frames) => decompress_frames_parallel(
+                    &input_file,
+                    &output_file,
+                    &frames,
+                    threads,
+                    &mut reporter,
+                    options,
+                )?,
+                None => {
+                    let mut input_file = input_file;
+                    input_file.rewind()?;
+                    let buffer_size = options.buffer_size();
+                    if options.sparse {
+                        let mut writer = SparseWriter::new(&output_file);
+                        decode_with_progress(input_file, &mut writer, buffer_size, &mut reporter)?;
+                        writer.finish()?
+                    } else {
+                        decode_with_progress(
+                            input_file,
+                            &mut output_file,
+                            buffer_size,
+                            &mut reporter,
+                        )?
+                    }
                 }
-                output_file.write_all(&buffer[..bytes_read])?;
-                bytes_processed += bytes_read as u64;
-                let _ = tx.send(CompressMessage::Progress {
-                    bytes_processed,
-                    total_bytes: compressed_size,
-                });
-            }
+            };
+
+            output_file.sync_all()?;
+            reporter.confirm()?;
+            let source_removed = remove_source_if_requested(&input_path, options)?;
 
             // Return compressed size first, decompressed second the Finished
             // handler in app.rs knows to flip the labels when is_decompressing is set
-            Ok((compressed_size, bytes_processed, output_path))
+            Ok((
+                compressed_size,
+                bytes_processed,
+                output_path.clone(),
+                source_removed,
+            ))
         };
 
         match run() {
-            Ok((compressed_size, decompressed_size, path)) => {
+            Ok((compressed_size, decompressed_size, path, source_removed)) => {
                 let _ = tx.send(CompressMessage::Finished {
                     original_size: compressed_size,
                     compressed_size: decompressed_size,
                     output_path: path,
+                    source_removed,
                 });
             }
             Err(e) => {
+                if output_created {
+                    remove_partial_output(&output_path);
+                }
                 let _ = tx.send(CompressMessage::Error(e.to_string()));
             }
         }
     });
 }
 
+/// The most a zstd frame can inflate per compressed byte: a block holds at
+/// most 128 KiB and takes at least 4 bytes.
+pub const MAX_EXPANSION: u64 = 128 * 1024 / 4;
+
+// Decodes each frame on a pool of `threads` workers and writes it straight to its
+// final offset in `output`, so frames can finish in any order. Each worker holds at
+// most one frame in memory at a time.
+fn decompress_frames_parallel(
+    input: &File,
+    output: &File,
+    frames: &[SeekEntry],
+    threads: usize,
+    reporter: &mut Reporter,
+    options: JobOptions,
+) -> std::io::Result<u64> {
+    // The sizes come from the file, so a crafted one can claim anything; a frame
+    // claiming more than `MAX_EXPANSION` allows can't be telling the truth.
+    if frames
+        .iter()
+        .any(|f| f.decompressed_size as u64 > f.compressed_size as u64 * MAX_EXPANSION)
+    {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "a frame claims more data than it could hold",
+        ));
+    }
+    let total_bytes = frames
+        .last()
+        .map_or(0, |f| f.decompressed_offset + f.decompressed_size as u64);
+    reporter.set_total(total_bytes);
+
+    let next_frame = AtomicUsize::new(0);
+    // Held while reporting, so the reports from all workers arrive in order
+    let progress = Mutex::new((0u64, reporter));
+
+    std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads.min(frames.len()))
+            .map(|_| {
+                let (next_frame, progress) = (&next_frame, &progress);
+                scope.spawn(move || -> std::io::Result<()> {
+                    let mut reader = PositionedReader::new(input);
+                    // Decodes and writes the next frame; false once there are none left
+                    let mut next = || -> std::io::Result<bool> {
+                        let index = next_frame.fetch_add(1, Ordering::Relaxed);
+                        let Some(frame) = frames.get(index) else {
+                            return Ok(false);
+                        };
+                        let data = decode_frame(&mut reader, frame)?;
+                        let offset = frame.decompressed_offset;
+                        // Writes past the end leave holes; a trailing one is sized below
+                        if options.sparse {
+                            for range in sparse::data_ranges(&data, offset) {
+                                write_all_at(
+                                    output,
+                                    &data[range.clone()],
+                                    offset + range.start as u64,
+                                )?;
+                            }
+                        } else {
+                            write_all_at(output, &data, offset)?;
+                        }
+
+                        let mut progress = progress.lock().expect("no worker panics holding it");
+                        let (done, reporter) = &mut *progress;
+                        *done += data.len() as u64;
+                        reporter.report(*done)?;
+                        Ok(true)
+                    };
+                    loop {
+                        match next() {
+                            Ok(true) => {}
+                            Ok(false) => return Ok(()),
+                            Err(e) => {
+                                // Stop handing out frames so the others wind down quickly
+                                next_frame.store(frames.len(), Ordering::Relaxed);
+                                return Err(e);
+                            }
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        let mut result = Ok(());
+        for worker in workers {
+            let outcome = worker.join().expect("decompression worker panicked");
+            result = result.and(outcome);
+        }
+        result
+    })?;
+
+    // Only once every frame decoded to the size it claimed, so the file never
+    // grows past what was actually written
+    output.set_len(total_bytes)?;
+    Ok(total_bytes)
+}
+
+// Read + Seek over a shared &File using positioned reads, so every worker
+// can read its own frames without fighting over one file cursor.
+struct PositionedReader<'a> {
+    file: &'a File,
+    position: u64,
+}
+
+impl<'a> PositionedReader<'a> {
+    fn new(file: &'a File) -> Self {
+        Self { file, position: 0 }
+    }
+}
+
+impl Read for PositionedReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = read_at(self.file, buf, self.position)?;
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
+impl Seek for PositionedReader<'_> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.position = match pos {
+            SeekFrom::Start(offset) => offset,
+            _ => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::Unsupported,
+                    "only absolute seeks are supported",
+                ));
+            }
+        };
+        Ok(self.position)
+    }
+}
+
+#[cfg(unix)]
+fn read_at(file: &File, buf: &mut [u8], offset: u64) -> std::io::Result<usize> {
+    std::os::unix::fs::FileExt::read_at(file, buf, offset)
+}
+
+#[cfg(windows)]
+fn read_at(file: &File, buf: &mut [u8], offset: u64) -> std::io::Result<usize> {
+    std::os::windows::fs::FileExt::seek_read(file, buf, offset)
+}
+
+#[cfg(unix)]
+fn write_all_at(file: &File, buf: &[u8], offset: u64) -> std::io::Result<()> {
+    std::os::unix::fs::FileExt::write_all_at(file, buf, offset)
+}
+
+#[cfg(windows)]
+fn write_all_at(file: &File, mut buf: &[u8], mut offset: u64) -> std::io::Result<()> {
+    while !buf.is_empty() {
+        let written = std::os::windows::fs::FileExt::seek_write(file, buf, offset)?;
+        if written == 0 {
+            return Err(std::io::ErrorKind::WriteZero.into());
+        }
+        buf = &buf[written..];
+        offset += written as u64;
+    }
+    Ok(())
+}
+
+// The input mapped into memory if `options.read` asks for it. `None` reads it
+// through a buffer instead: it is small, stored (the kernel copies those), or
+// can't be mapped, like a pipe.
+fn map_input(file: &File, len: u64, options: JobOptions) -> Option<Mmap> {
+    if options.algorithm == Algorithm::Store || !options.read.maps(len) {
+        return None;
+    }
+    if options.read == ReadMode::Auto && writable_by_others(file) {
+        return None;
+    }
+    // SAFETY: the map is only read, and only while this job runs. If another
+    // process truncates the file meanwhile, reading the lost pages raises SIGBUS,
+    // which kills the whole process, not just this job, and leaves the TUI's
+    // terminal in raw mode. `Auto` keeps clear of files others can write to;
+    // `ReadMode::Buffered` avoids the risk altogether.
+    let map = unsafe { Mmap::map(file) }.ok()?;
+    #[cfg(unix)]
+    let _ = map.advise(memmap2::Advice::Sequential);
+    Some(map)
+}
+
+// Whether users other than the owner may write to the file, and so truncate
+// it under a map. Windows refuses to truncate a mapped file in the first place.
+#[cfg(unix)]
+fn writable_by_others(file: &File) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    file.metadata()
+        .map_or(true, |m| m.permissions().mode() & 0o022 != 0)
+}
+
+#[cfg(not(unix))]
+fn writable_by_others(_file: &File) -> bool {
+    false
+}
+
+// Writes the input, mapped or else read from the file, to `writer` in chunks
+// of `buffer_size`, reporting progress after each one.
+fn feed<W: Write>(
+    file: &mut File,
+    mapped: Option<&[u8]>,
+    writer: &mut W,
+    buffer_size: usize,
+    reporter: &mut Reporter,
+) -> std::io::Result<u64> {
+    let Some(data) = mapped else {
+        return copy_with_progress(file, writer, buffer_size, reporter);
+    };
+    let mut bytes_processed = 0;
+    for chunk in data.chunks(buffer_size) {
+        writer.write_all(chunk)?;
+        bytes_processed += chunk.len() as u64;
+        reporter.report(bytes_processed)?;
+    }
+    Ok(bytes_processed)
+}
+
+// Pumps `reader` into `writer` in chunks of `buffer_size`, reporting progress
+// after each one. Stops once nobody listens.
+fn copy_with_progress<R: Read, W: Write>(
+    reader: &mut R,
+    writer: &mut W,
+    buffer_size: usize,
+    reporter: &mut Reporter,
+) -> std::io::Result<u64> {
+    let mut buffer = vec![0u8; buffer_size];
+    let mut bytes_processed: u64 = 0;
+
+    loop {
+        let bytes_read = reader.read(&mut buffer)?;
+        if bytes_read == 0 {
+            break;
+        }
+        writer.write_all(&buffer[..bytes_read])?;
+        bytes_processed += bytes_read as u64;
+        reporter.report(bytes_processed)?;
+    }
+    Ok(bytes_processed)
+}
+
+// Decodes `input` into `writer` through a single streaming decoder and returns
+// the decoded size. The decoded size isn't known up front, so progress counts
+// the compressed bytes consumed, out of the compressed size.
+fn decode_with_progress<R: Read, W: Write>(
+    input: R,
+    writer: &mut W,
+    buffer_size: usize,
+    reporter: &mut Reporter,
+) -> std::io::Result<u64> {
+    let consumed = Cell::new(0);
+    let input = CountingReader {
+        inner: input,
+        count: &consumed,
+    };
+    // BufReader here because Decoder does many small reads internally
+    let mut decoder = Decoder::new(BufReader::with_capacity(buffer_size, input))?;
+    let mut buffer = vec![0u8; buffer_size];
+    let mut bytes_written = 0;
+
+    loop {
+        let bytes_read = decoder.read(&mut buffer)?;
+        if bytes_read == 0 {
+            break;
+        }
+        writer.write_all(&buffer[..bytes_read])?;
+        bytes_written += bytes_read as u64;
+        reporter.report(consumed.get())?;
+    }
+    Ok(bytes_written)
+}
+
+struct CountingReader<'a, R> {
+    inner: R,
+    count: &'a Cell<u64>,
+}
+
+impl<R: Read> Read for CountingReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.count.set(self.count.get() + read as u64);
+        Ok(read)
+    }
+}
+
+// A failed or stopped job leaves a half-written output behind; nothing else
+// would clean it up.
+fn remove_partial_output(output_path: &str) {
+    let _ = std::fs::remove_file(output_path);
+}
+
+// Creates the output file, refusing to replace an existing one under
+// `OverwritePolicy::Never`, and the input itself under any policy.
+fn create_output(
+    input_path: &str,
+    path: &str,
+    overwrite: OverwritePolicy,
+) -> std::io::Result<File> {
+    if crate::output::same_file(Path::new(input_path), Path::new(path))? {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("{} would overwrite the input", path),
+        ));
+    }
+    let mut open = OpenOptions::new();
+    match overwrite {
+        OverwritePolicy::Always => open.write(true).create(true).truncate(true),
+        OverwritePolicy::Never => open.write(true).create_new(true),
+    };
+    open.open(path).map_err(|e| match e.kind() {
+        std::io::ErrorKind::AlreadyExists => std::io::Error::new(
+            e.kind(),
+            format!("{} already exists (overwrite = never)", path),
+        ),
+        _ => e,
+    })
+}
+
+// Only called once the output has been synced (and verified, if asked), so a
+// crash or failure before this point always leaves the source in place.
+fn remove_source_if_requested(input_path: &str, options: JobOptions) -> std::io::Result<bool> {
+    if !options.remove_source {
+        return Ok(false);
+    }
+    std::fs::remove_file(input_path)?;
+    Ok(true)
+}
+
+/// Decodes `compressed_path` and checks it matches `original_path` byte for
+/// byte, stopping like the job it belongs to once nobody watches `reporter`.
+pub fn verify_output(
+    original_path: &str,
+    compressed_path: &str,
+    reporter: &mut Reporter,
+) -> std::io::Result<()> {
+    let mut original = BufReader::new(std::fs::File::open(original_path)?);
+    let mut decoder = Decoder::new(BufReader::new(std::fs::File::open(compressed_path)?))?;
+    let mut expected = vec![0u8; 64 * 1024];
+    let mut actual = vec![0u8; 64 * 1024];
+
+    loop {
+        reporter.check()?;
+        let expected_len = read_full(&mut original, &mut expected)?;
+        let actual_len = read_full(&mut decoder, &mut actual)?;
+        if expected[..expected_len] != actual[..actual_len] {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "verification failed: output does not decode to the input",
+            ));
+        }
+        if expected_len == 0 {
+            return Ok(());
+        }
+    }
+}
+
+// Like read_exact, but a short read at EOF is fine — returns how much was filled.
+fn read_full<R: Read>(reader: &mut R, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..])? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+    Ok(filled)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::BUFFER_SIZES;
+    use crate::progress::ProgressMode;
+    use proptest::prelude::*;
+    use proptest::test_runner::TestCaseError;
     use std::sync::mpsc;
 
+    /// The smallest buffer the property test picks besides the minimum.
+    const CHUNK_SIZE: usize = 64 * 1024;
+
     // make sure we get the exact same bytes back.
     #[test]
     fn compress_then_decompress_roundtrip() {
@@ -131,6 +611,7 @@ mod tests {
             compressed_path.to_string_lossy().to_string(),
             tx,
             crate::CompressionLevel::Normal,
+            JobOptions::default(),
         );
 
         let mut finished = false;
@@ -151,6 +632,7 @@ mod tests {
             compressed_path.to_string_lossy().to_string(),
             decompressed_path.to_string_lossy().to_string(),
             tx,
+            JobOptions::default(),
         );
 
         let mut finished = false;
@@ -178,4 +660,416 @@ mod tests {
         // Don't leave temp files lying around
         std::fs::remove_dir_all(&dir).ok();
     }
+
+    // --rm must only delete the input after a verified, complete output,
+    // and must leave it alone when the job fails.
+    #[test]
+    fn remove_source_only_after_success() {
+        let dir = std::env::temp_dir().join("freya_test_remove_source");
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let input_path = dir.join("input.txt");
+        let data = b"remove me once it's safe\n".repeat(200);
+        std::fs::write(&input_path, &data).unwrap();
+
+        let options = JobOptions {
+            remove_source: true,
+            verify: true,
+            ..JobOptions::default()
+        };
+
+        // Output into a directory that doesn't exist: the job fails, the source stays
+        let (tx, rx) = mpsc::channel();
+        start_compression(
+            input_path.to_string_lossy().to_string(),
+            dir.join("missing")
+                .join("out.zst")
+                .to_string_lossy()
+                .to_string(),
+            tx,
+            crate::CompressionLevel::Fast,
+            options,
+        );
+        assert!(
+            rx.iter()
+                .any(|msg| matches!(msg, CompressMessage::Error(_)))
+        );
+        assert!(input_path.exists(), "source removed after a failed job");
+
+        let output_path = compressed_path(&input_path);
+        let (tx, rx) = mpsc::channel();
+        start_compression(
+            input_path.to_string_lossy().to_string(),
+            output_path.to_string_lossy().to_string(),
+            tx,
+            crate::CompressionLevel::Fast,
+            options,
+        );
+        let removed = rx.iter().find_map(|msg| match msg {
+            CompressMessage::Finished { source_removed, .. } => Some(source_removed),
+            CompressMessage::Error(e) => panic!("Compression failed: {}", e),
+            CompressMessage::Progress { .. } => None,
+            CompressMessage::Counter(_) | CompressMessage::BatchFinished(_) => unreachable!(),
+        });
+        assert_eq!(removed, Some(true));
+        assert!(!input_path.exists(), "source still present after --rm");
+        assert_eq!(
+            zstd::decode_all(std::fs::File::open(&output_path).unwrap()).unwrap(),
+            data
+        );
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    // An output that is the input (here spelled differently) fails the job
+    // before the input is truncated.
+    #[test]
+    fn refuses_to_overwrite_the_input() {
+        let dir = std::env::temp_dir().join("freya_test_same_file");
+        std::fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("blob");
+        let data = zstd::encode_all(&b"not empty"[..], 1).unwrap();
+        std::fs::write(&input, &data).unwrap();
+        let same = dir.join(".").join("blob").to_string_lossy().to_string();
+
+        let (tx, rx) = mpsc::channel();
+        let input_path = input.to_string_lossy().to_string();
+        start_compression(
+            input_path.clone(),
+            same.clone(),
+            tx,
+            CompressionLevel::Fast,
+            JobOptions::default(),
+        );
+        assert!(matches!(rx.recv(), Ok(CompressMessage::Error(_))));
+        let (tx, rx) = mpsc::channel();
+        start_decompression(input_path, same, tx, JobOptions::default());
+        assert!(matches!(rx.recv(), Ok(CompressMessage::Error(_))));
+        assert_eq!(std::fs::read(&input).unwrap(), data);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    // Dropping the receiver stops a job after the chunk in flight.
+    #[test]
+    fn copy_stops_once_nobody_listens() {
+        let data = vec![7u8; 256 * 1024];
+        let (tx, rx) = mpsc::channel();
+        let mut reporter = Reporter::new(&tx, data.len() as u64, ProgressMode::Messages).unwrap();
+        drop(rx);
+        let mut output = Vec::new();
+        let e = copy_with_progress(&mut data.as_slice(), &mut output, CHUNK_SIZE, &mut reporter)
+            .unwrap_err();
+        assert_eq!(e.kind(), std::io::ErrorKind::Interrupted);
+        assert_eq!(output.len(), CHUNK_SIZE);
+
+        let dir = std::env::temp_dir().join("freya_test_stop");
+        std::fs::create_dir_all(&dir).unwrap();
+
+        // Verification sends no progress, but still stops
+        let (original, compressed) = (dir.join("original"), dir.join("original.zst"));
+        std::fs::write(&original, &data).unwrap();
+        std::fs::write(&compressed, zstd::encode_all(&data[..], 1).unwrap()).unwrap();
+        let (tx, rx) = mpsc::channel();
+        let mut reporter = Reporter::new(&tx, 0, ProgressMode::Counter).unwrap();
+        let path = |p: &Path| p.to_string_lossy().to_string();
+        verify_output(&path(&original), &path(&compressed), &mut reporter).unwrap();
+        drop(rx);
+        let e = verify_output(&path(&original), &path(&compressed), &mut reporter).unwrap_err();
+        assert_eq!(e.kind(), std::io::ErrorKind::Interrupted);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    // `Auto` maps big inputs, but not ones others could truncate under the map.
+    #[cfg(unix)]
+    #[test]
+    fn auto_maps_only_files_nobody_else_writes() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = std::env::temp_dir().join("freya_test_map");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("big.bin");
+        File::create(&path)
+            .unwrap()
+            .set_len(crate::MAPPED_MIN_SIZE)
+            .unwrap();
+        let file = File::open(&path).unwrap();
+        let maps = |read| {
+            let options = JobOptions {
+                read,
+                ..JobOptions::default()
+            };
+            map_input(&file, crate::MAPPED_MIN_SIZE, options).is_some()
+        };
+
+        let set_mode =
+            |mode| std::fs::set_permissions(&path, std::fs::Permissions::from_mode(mode)).unwrap();
+        set_mode(0o644);
+        assert!(maps(ReadMode::Auto));
+        set_mode(0o666);
+        assert!(!maps(ReadMode::Auto));
+        assert!(maps(ReadMode::Mapped));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    // Seekable output has many independent frames, so decompression takes the
+    // parallel path; frames must still land at the right offsets.
+    #[test]
+    fn parallel_decompression_of_seekable_output() {
+        let dir = std::env::temp_dir().join("freya_test_parallel");
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let input_path = dir.join("input.bin");
+        let compressed = dir.join("input.bin.zst");
+        let output_path = dir.join("output.bin");
+        let data: Vec<u8> = (0..300_000u32)
+            .flat_map(|i| (i / 7).to_le_bytes())
+            .collect();
+        std::fs::write(&input_path, &data).unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        start_compression(
+            input_path.to_string_lossy().to_string(),
+            compressed.to_string_lossy().to_string(),
+            tx,
+            crate::CompressionLevel::Fast,
+            JobOptions {
+                seekable_frame_size: Some(50_000),
+                ..JobOptions::default()
+            },
+        );
+        assert!(
+            rx.iter()
+                .any(|msg| matches!(msg, CompressMessage::Finished { .. }))
+        );
+
+        let (tx, rx) = mpsc::channel();
+        start_decompression(
+            compressed.to_string_lossy().to_string(),
+            output_path.to_string_lossy().to_string(),
+            tx,
+            JobOptions {
+                threads: 4,
+                ..JobOptions::default()
+            },
+        );
+        let mut last_progress = 0;
+        for msg in rx {
+            match msg {
+                CompressMessage::Progress {
+                    bytes_processed, ..
+                } => last_progress = last_progress.max(bytes_processed),
+                CompressMessage::Finished {
+                    compressed_size, ..
+                } => {
+                    assert_eq!(compressed_size, data.len() as u64);
+                    break;
+                }
+                CompressMessage::Error(e) => panic!("Decompression failed: {}", e),
+                CompressMessage::Counter(_) | CompressMessage::BatchFinished(_) => unreachable!(),
+            }
+        }
+        assert_eq!(last_progress, data.len() as u64);
+        assert_eq!(std::fs::read(&output_path).unwrap(), data);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    // A seek table lying about frame sizes fails the job before the output grows
+    // past what was decoded, and the partial output goes away with it.
+    #[test]
+    fn parallel_decompression_rejects_inflated_claims() {
+        let dir = std::env::temp_dir().join("freya_test_claims");
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut seekable = Vec::new();
+        let mut writer = SeekableWriter::new(&mut seekable, 1, 1000).unwrap();
+        writer.write_all(&[3u8; 4000]).unwrap();
+        writer.finish().unwrap();
+        let table =
+            crate::seekable::SeekTable::read_from(&mut std::io::Cursor::new(&seekable)).unwrap();
+        let entries_start = table.compressed_len() as usize + 8;
+
+        // Far beyond what the frame could hold, then just a little more than it does
+        for claim in [u32::MAX, 1001] {
+            let mut crafted = seekable.clone();
+            let second = entries_start + 8 + 4;
+            crafted[second..second + 4].copy_from_slice(&claim.to_le_bytes());
+            let (input, output) = (dir.join("crafted.zst"), dir.join("crafted"));
+            std::fs::write(&input, &crafted).unwrap();
+
+            let (tx, rx) = mpsc::channel();
+            start_decompression(
+                input.to_string_lossy().to_string(),
+                output.to_string_lossy().to_string(),
+                tx,
+                JobOptions {
+                    threads: 4,
+                    ..JobOptions::default()
+                },
+            );
+            assert!(
+                rx.iter()
+                    .any(|msg| matches!(msg, CompressMessage::Error(_))),
+                "claim of {} accepted",
+                claim
+            );
+            assert!(!output.exists(), "partial output left behind");
+        }
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    // Progress reports up to the end of a job, and the sizes it finished with.
+    // A job with a counter reports once, what the counter says at the end.
+    fn drain(rx: mpsc::Receiver<CompressMessage>) -> (Vec<(u64, u64)>, (u64, u64)) {
+        let mut progress = Vec::new();
+        let mut counter = None;
+        for msg in rx {
+            match msg {
+                CompressMessage::Progress {
+                    bytes_processed,
+                    total_bytes,
+                } => progress.push((bytes_processed, total_bytes)),
+                CompressMessage::Counter(shared) => counter = Some(shared),
+                CompressMessage::Finished {
+                    original_size,
+                    compressed_size,
+                    ..
+                } => {
+                    if let Some(counter) = counter {
+                        assert!(progress.is_empty(), "counter jobs sent messages");
+                        // Untouched, like a job that sent no messages
+                        if counter.bytes_processed() > 0 {
+                            progress.push((counter.bytes_processed(), counter.total_bytes()));
+                        }
+                    }
+                    return (progress, (original_size, compressed_size));
+                }
+                CompressMessage::Error(e) => panic!("job failed: {}", e),
+                CompressMessage::BatchFinished(_) => unreachable!(),
+            }
+        }
+        panic!("job ended without Finished");
+    }
+
+    // Counts never go backwards, always measure against the same total, and
+    // the last report is the whole job. Empty jobs report nothing.
+    fn check_progress(progress: &[(u64, u64)]) -> Result<(), TestCaseError> {
+        let Some(&(last, total)) = progress.last() else {
+            return Ok(());
+        };
+        prop_assert!(progress.iter().all(|&(_, t)| t == total), "{:?}", progress);
+        prop_assert!(
+            progress.is_sorted_by_key(|&(done, _)| done),
+            "{:?}",
+            progress
+        );
+        prop_assert_eq!(last, total);
+        Ok(())
+    }
+
+    fn inputs() -> impl Strategy<Value = Vec<u8>> {
+        let around_chunk = [1, 2, 3].map(|n| n * CHUNK_SIZE).map(|n| n - 1..=n + 1);
+        prop_oneof![
+            Just(Vec::new()),
+            any::<u8>().prop_map(|byte| vec![byte]),
+            prop::sample::select(around_chunk.into_iter().flatten().collect::<Vec<_>>())
+                .prop_flat_map(|len| prop::collection::vec(any::<u8>(), len)),
+            prop::collection::vec(any::<u8>(), 0..100_000),
+            (prop::collection::vec(any::<u8>(), 1..16), 0..400_000usize)
+                .prop_map(|(pattern, len)| pattern.iter().copied().cycle().take(len).collect()),
+            // Like a disk image: zeros with a few bytes set
+            (
+                1..400_000usize,
+                prop::collection::vec(any::<(usize, u8)>(), 0..8)
+            )
+                .prop_map(|(len, set)| {
+                    let mut data = vec![0; len];
+                    for (at, byte) in set {
+                        data[at % len] = byte;
+                    }
+                    data
+                }),
+        ]
+    }
+
+    fn options() -> impl Strategy<Value = JobOptions> {
+        let layouts = prop_oneof![
+            Just((Algorithm::Zstd, None)),
+            prop::sample::select(vec![1000, 50_000, CHUNK_SIZE as u32])
+                .prop_map(|size| (Algorithm::Zstd, Some(size))),
+            Just((Algorithm::Store, None)),
+        ];
+        let buffer_sizes = prop::sample::select(vec![*BUFFER_SIZES.start(), CHUNK_SIZE, 0]);
+        let modes = prop::sample::select(vec![ProgressMode::Messages, ProgressMode::Counter]);
+        let reads = prop::sample::select(vec![ReadMode::Buffered, ReadMode::Mapped]);
+        (
+            layouts,
+            any::<(bool, bool)>(),
+            prop::sample::select(vec![1, 4]),
+            buffer_sizes,
+            modes,
+            reads,
+        )
+            .prop_map(
+                |(
+                    (algorithm, seekable_frame_size),
+                    (checksum, sparse),
+                    threads,
+                    buffer_size,
+                    progress,
+                    read,
+                )| {
+                    JobOptions {
+                        algorithm,
+                        seekable_frame_size,
+                        checksum,
+                        threads,
+                        buffer_size,
+                        progress,
+                        read,
+                        sparse,
+                        ..JobOptions::default()
+                    }
+                },
+            )
+    }
+
+    static CASE: AtomicUsize = AtomicUsize::new(0);
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(48))]
+
+        #[test]
+        fn roundtrips_every_shape_level_and_layout(
+            data in inputs(),
+            level in prop::sample::select(CompressionLevel::ALL.to_vec()),
+            options in options(),
+        ) {
+            let case = CASE.fetch_add(1, Ordering::Relaxed);
+            let dir = std::env::temp_dir().join(format!("freya_test_prop_{}", case));
+            std::fs::create_dir_all(&dir).unwrap();
+            let input = dir.join("input.bin");
+            let compressed = dir.join("input.bin.zst");
+            let output = dir.join("output.bin");
+            std::fs::write(&input, &data).unwrap();
+            let path = |p: &Path| p.to_string_lossy().to_string();
+
+            let (tx, rx) = mpsc::channel();
+            start_compression(path(&input), path(&compressed), tx, level, options);
+            let (progress, sizes) = drain(rx);
+            check_progress(&progress)?;
+            let compressed_size = std::fs::metadata(&compressed).unwrap().len();
+            prop_assert_eq!(sizes, (data.len() as u64, compressed_size));
+
+            let (tx, rx) = mpsc::channel();
+            start_decompression(path(&compressed), path(&output), tx, options);
+            let (progress, sizes) = drain(rx);
+            check_progress(&progress)?;
+            prop_assert_eq!(sizes, (compressed_size, data.len() as u64));
+            prop_assert!(std::fs::read(&output).unwrap() == data, "output differs from the input");
+
+            std::fs::remove_dir_all(&dir).ok();
+        }
+    }
 }
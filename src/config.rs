@@ -0,0 +1,343 @@
+//! User defaults, read at startup from `config.toml` in the XDG config dir
+//! (`~/.config/freya/config.toml` on Linux). Environment variables override the
+//! file, and command-line flags override both.
+
+use crate::batch::CompressedPolicy;
+use crate::keymap::Keymap;
+use crate::output::{OutputNaming, validate_template};
+use crate::theme::{Theme, ThemeSpec};
+use crate::{
+    Algorithm, CompressionLevel, JobOptions, OverwritePolicy, ReadMode, parse_buffer_size,
+    validate_buffer_size,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Points Freya at a different config file.
+pub const CONFIG_ENV: &str = "FREYA_CONFIG";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub level: CompressionLevel,
+    pub algorithm: Algorithm,
+    /// Decompression threads; 0 picks one per core.
+    pub threads: usize,
+    /// Bytes jobs read and write at a time; 0 picks the default, 1 MiB.
+    pub buffer_size: usize,
+    /// How inputs are read: mapped from 64 MiB up (`auto`), `buffered` or `mapped`.
+    pub read: ReadMode,
+    /// Leave holes for blocks of zeros when decompressing, e.g. in disk images.
+    pub sparse: bool,
+    /// Write outputs here instead of next to the input.
+    pub output_dir: Option<PathBuf>,
+    /// File name template for compressed outputs, e.g. `{stem}-{date}.{ext}.zst`.
+    pub output_template: Option<String>,
+    pub overwrite: OverwritePolicy,
+    pub verify: bool,
+    pub checksum: bool,
+    /// Long-distance matching, for big inputs with repeats far apart.
+    pub long_distance: bool,
+    pub remove_source: bool,
+    /// Quit the TUI two seconds after a job finishes.
+    pub auto_exit: bool,
+    /// What folder jobs do with files that are already compressed.
+    pub on_compressed: CompressedPolicy,
+    /// A built-in theme or one of `themes`; unset is dark, or monochrome under `NO_COLOR`.
+    pub theme: Option<String>,
+    /// Custom themes by name.
+    pub themes: BTreeMap<String, ThemeSpec>,
+    /// Key overrides: action name to keys, e.g. `quit = "ctrl+q, g q"`.
+    pub keys: BTreeMap<String, String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            level: CompressionLevel::default(),
+            algorithm: Algorithm::default(),
+            threads: 0,
+            buffer_size: 0,
+            read: ReadMode::default(),
+            sparse: true,
+            output_dir: None,
+            output_template: None,
+            overwrite: OverwritePolicy::default(),
+            verify: false,
+            checksum: false,
+            long_distance: false,
+            remove_source: false,
+            auto_exit: true,
+            on_compressed: CompressedPolicy::default(),
+            theme: None,
+            themes: BTreeMap::new(),
+            keys: BTreeMap::new(),
+        }
+    }
+}
+
+impl Config {
+    /// `$FREYA_CONFIG`, or `freya/config.toml` under the platform config dir.
+    pub fn path() -> Option<PathBuf> {
+        match std::env::var_os(CONFIG_ENV) {
+            Some(path) => Some(PathBuf::from(path)),
+            None => dirs::config_dir().map(|dir| dir.join("freya").join("config.toml")),
+        }
+    }
+
+    /// The config file (defaults if there is none) with environment overrides applied.
+    pub fn load() -> io::Result<Self> {
+        Self::load_file()?.with_env()
+    }
+
+    /// The config file alone, or the defaults if there is none.
+    pub fn load_file() -> io::Result<Self> {
+        match Self::path() {
+            Some(path) => Self::read(&path),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// This config with the process's environment overrides applied.
+    pub fn with_env(mut self) -> io::Result<Self> {
+        self.apply_env(|name| std::env::var(name).ok())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        Ok(self)
+    }
+
+    /// Parses `path`; a missing file gives the defaults.
+    pub fn read(path: &Path) -> io::Result<Self> {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e),
+        };
+        let config: Self = toml::from_str(&text).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {}", path.display(), e),
+            )
+        })?;
+        if let Some(template) = &config.output_template {
+            validate_template(template).map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{}: output_template: {}", path.display(), e),
+                )
+            })?;
+        }
+        validate_buffer_size(config.buffer_size).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: buffer_size: {}", path.display(), e),
+            )
+        })?;
+        if let Some(theme) = &config.theme {
+            Theme::named(theme, &config.themes).map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{}: theme: {}", path.display(), e),
+                )
+            })?;
+        }
+        Keymap::from_config(&config.keys).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: keys: {}", path.display(), e),
+            )
+        })?;
+        Ok(config)
+    }
+
+    /// Writes the config back to `Config::path()`, creating the directory if needed.
+    pub fn save(&self) -> io::Result<PathBuf> {
+        let path = Self::path().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                "no config directory on this platform",
+            )
+        })?;
+        self.write(&path)?;
+        Ok(path)
+    }
+
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        let text = toml::to_string_pretty(self)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, text)
+    }
+
+    /// Applies `FREYA_LEVEL`, `FREYA_ALGORITHM`, `FREYA_THREADS`, `FREYA_BUFFER_SIZE`,
+    /// `FREYA_READ`, `FREYA_SPARSE`, `FREYA_OUTPUT_DIR`, `FREYA_OUTPUT_TEMPLATE`,
+    /// `FREYA_OVERWRITE`, `FREYA_VERIFY`, `FREYA_CHECKSUM`, `FREYA_LONG_DISTANCE`,
+    /// `FREYA_REMOVE_SOURCE`, `FREYA_AUTO_EXIT`, `FREYA_ON_COMPRESSED` and `FREYA_THEME`,
+    /// looked up through `var`.
+    pub fn apply_env(&mut self, var: impl Fn(&str) -> Option<String>) -> Result<(), String> {
+        fn parse<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, String>
+        where
+            T::Err: std::fmt::Display,
+        {
+            value
+                .parse()
+                .map_err(|e| format!("{}={}: {}", name, value, e))
+        }
+        fn parse_bool(name: &str, value: &str) -> Result<bool, String> {
+            match value.to_ascii_lowercase().as_str() {
+                "1" | "true" | "yes" | "on" => Ok(true),
+                "0" | "false" | "no" | "off" => Ok(false),
+                _ => Err(format!("{}={}: expected true or false", name, value)),
+            }
+        }
+
+        let get = |name: &'static str| var(name).map(|value| (name, value));
+        if let Some((name, value)) = get("FREYA_LEVEL") {
+            self.level = parse(name, &value)?;
+        }
+        if let Some((name, value)) = get("FREYA_ALGORITHM") {
+            self.algorithm = parse(name, &value)?;
+        }
+        if let Some((name, value)) = get("FREYA_THREADS") {
+            self.threads = parse(name, &value)?;
+        }
+        if let Some((name, value)) = get("FREYA_BUFFER_SIZE") {
+            self.buffer_size =
+                parse_buffer_size(&value).map_err(|e| format!("{}={}: {}", name, value, e))?;
+        }
+        if let Some((name, value)) = get("FREYA_READ") {
+            self.read = parse(name, &value)?;
+        }
+        if let Some((name, value)) = get("FREYA_SPARSE") {
+            self.sparse = parse_bool(name, &value)?;
+        }
+        if let Some((_, value)) = get("FREYA_OUTPUT_DIR") {
+            // An empty value switches a configured output dir back off
+            self.output_dir = (!value.is_empty()).then(|| PathBuf::from(value));
+        }
+        if let Some((name, value)) = get("FREYA_OUTPUT_TEMPLATE") {
+            validate_template(&value).map_err(|e| format!("{}={}: {}", name, value, e))?;
+            self.output_template = Some(value);
+        }
+        if let Some((name, value)) = get("FREYA_OVERWRITE") {
+            self.overwrite = parse(name, &value)?;
+        }
+        if let Some((name, value)) = get("FREYA_VERIFY") {
+            self.verify = parse_bool(name, &value)?;
+        }
+        if let Some((name, value)) = get("FREYA_CHECKSUM") {
+            self.checksum = parse_bool(name, &value)?;
+        }
+        if let Some((name, value)) = get("FREYA_LONG_DISTANCE") {
+            self.long_distance = parse_bool(name, &value)?;
+        }
+        if let Some((name, value)) = get("FREYA_REMOVE_SOURCE") {
+            self.remove_source = parse_bool(name, &value)?;
+        }
+        if let Some((name, value)) = get("FREYA_AUTO_EXIT") {
+            self.auto_exit = parse_bool(name, &value)?;
+        }
+        if let Some((name, value)) = get("FREYA_ON_COMPRESSED") {
+            self.on_compressed = parse(name, &value)?;
+        }
+        if let Some((name, value)) = get("FREYA_THEME") {
+            Theme::named(&value, &self.themes).map_err(|e| format!("{}={}: {}", name, value, e))?;
+            self.theme = Some(value);
+        }
+        Ok(())
+    }
+
+    pub fn naming(&self) -> OutputNaming {
+        OutputNaming {
+            dir: self.output_dir.clone(),
+            template: self.output_template.clone(),
+        }
+    }
+
+    /// The theme to draw with, given whether `NO_COLOR` is set.
+    pub fn theme(&self, no_color: bool) -> Result<Theme, String> {
+        Theme::from_config(self.theme.as_deref(), &self.themes, no_color)
+    }
+
+    pub fn job_options(&self) -> JobOptions {
+        JobOptions {
+            algorithm: self.algorithm,
+            remove_source: self.remove_source,
+            verify: self.verify,
+            threads: self.threads,
+            buffer_size: self.buffer_size,
+            read: self.read,
+            sparse: self.sparse,
+            overwrite: self.overwrite,
+            checksum: self.checksum,
+            long_distance: self.long_distance,
+            ..JobOptions::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // File values replace the defaults, env values replace the file, and
+    // whatever we save reads back the same.
+    #[test]
+    fn file_then_env_overrides() {
+        let mut config: Config = toml::from_str(
+            r#"
+            level = "best"
+            threads = 4
+            verify = true
+            on_compressed = "store"
+
+            [keys]
+            quit = "ctrl+c"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.level, CompressionLevel::Best);
+        assert_eq!(config.algorithm, Algorithm::Zstd);
+        assert_eq!(config.on_compressed, CompressedPolicy::Store);
+        assert_eq!(config.keys["quit"], "ctrl+c");
+
+        let env = |name: &str| match name {
+            "FREYA_LEVEL" => Some("fast".to_string()),
+            "FREYA_VERIFY" => Some("no".to_string()),
+            "FREYA_OUTPUT_DIR" => Some("/tmp/out".to_string()),
+            "FREYA_BUFFER_SIZE" => Some("256K".to_string()),
+            _ => None,
+        };
+        config.apply_env(env).unwrap();
+        assert_eq!(config.level, CompressionLevel::Fast);
+        assert!(!config.verify);
+        assert_eq!(config.threads, 4);
+        assert_eq!(config.job_options().buffer_size, 256 * 1024);
+        assert_eq!(config.output_dir, Some(PathBuf::from("/tmp/out")));
+        assert!(
+            config
+                .apply_env(|name| (name == "FREYA_THREADS").then(|| "many".to_string()))
+                .is_err()
+        );
+        assert!(
+            config
+                .apply_env(|name| (name == "FREYA_BUFFER_SIZE").then(|| "1K".to_string()))
+                .is_err()
+        );
+
+        let dir = std::env::temp_dir().join("freya_test_config");
+        let path = dir.join("nested").join("config.toml");
+        config.write(&path).unwrap();
+        assert_eq!(Config::read(&path).unwrap(), config);
+        assert_eq!(
+            Config::read(&dir.join("missing.toml")).unwrap(),
+            Config::default()
+        );
+        assert!(toml::from_str::<Config>("levle = \"best\"").is_err());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
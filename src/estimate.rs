@@ -0,0 +1,153 @@
+//! Predicts how well a file will compress from a small sample, so the user can
+//! decide whether a big job is worth running.
+
+use crate::CompressionLevel;
+use crate::bench::load_sample;
+use crate::human_bytes;
+use crate::sniff::{self, CompressedFormat};
+use std::io;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+/// Bytes compressed for an estimate, spread across the file.
+pub const ESTIMATE_SAMPLE_SIZE: u64 = 1024 * 1024;
+/// Sample entropy (bits per byte) above which we call the data incompressible.
+pub const HIGH_ENTROPY: f64 = 7.9;
+/// Outputs above this percentage of the input aren't worth the CPU time.
+pub const POOR_RATIO: f64 = 97.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Estimate {
+    pub level: CompressionLevel,
+    pub file_size: u64,
+    pub sample_size: u64,
+    pub sample_compressed: u64,
+    pub sample_time: Duration,
+    /// Shannon entropy of the sample, in bits per byte.
+    pub entropy: f64,
+    /// Format recognised from the file's magic bytes, if it's a compressed one.
+    pub format: Option<CompressedFormat>,
+}
+
+impl Estimate {
+    /// Predicted output size as a percentage of the input.
+    pub fn ratio(&self) -> f64 {
+        if self.sample_size == 0 {
+            return 100.0;
+        }
+        self.sample_compressed as f64 / self.sample_size as f64 * 100.0
+    }
+
+    pub fn predicted_size(&self) -> u64 {
+        (self.file_size as f64 * self.ratio() / 100.0) as u64
+    }
+
+    /// Single-threaded time for the whole file, scaled up from the sample.
+    pub fn predicted_time(&self) -> Duration {
+        if self.sample_size == 0 {
+            return Duration::ZERO;
+        }
+        self.sample_time
+            .mul_f64(self.file_size as f64 / self.sample_size as f64)
+    }
+
+    pub fn already_compressed(&self) -> bool {
+        self.format.is_some() || self.entropy >= HIGH_ENTROPY || self.ratio() >= POOR_RATIO
+    }
+
+    pub fn warning(&self) -> Option<String> {
+        match self.format {
+            Some(format) => Some(format!("already compressed ({})", format.name())),
+            None if self.already_compressed() => Some(format!(
+                "looks already compressed ({:.2} bits/byte)",
+                self.entropy
+            )),
+            None => None,
+        }
+    }
+
+    /// One-line summary for the status block.
+    pub fn summary(&self) -> String {
+        let mut summary = format!(
+            "~{} ({:.1}%) in ~{:.1}s at {}",
+            human_bytes(self.predicted_size()),
+            self.ratio(),
+            self.predicted_time().as_secs_f64(),
+            self.level.label()
+        );
+        if let Some(warning) = self.warning() {
+            summary.push_str(&format!(" | ⚠ {}", warning));
+        }
+        summary
+    }
+}
+
+pub fn estimate(path: &str, level: CompressionLevel) -> io::Result<Estimate> {
+    let (sample, file_size) = load_sample(path, ESTIMATE_SAMPLE_SIZE)?;
+
+    let started = Instant::now();
+    let compressed = zstd::bulk::compress(&sample, level.zstd_level())?;
+    let sample_time = started.elapsed();
+
+    // The first sample chunk starts at offset 0, so it holds the file header
+    Ok(Estimate {
+        level,
+        file_size,
+        sample_size: sample.len() as u64,
+        sample_compressed: compressed.len() as u64,
+        sample_time,
+        entropy: sniff::entropy(&sample),
+        format: sniff::sniff(&sample),
+    })
+}
+
+/// Runs `estimate` on a background thread; Best can take a moment even on a sample.
+pub fn start_estimate(
+    path: String,
+    level: CompressionLevel,
+    tx: mpsc::Sender<Result<Estimate, String>>,
+) {
+    std::thread::spawn(move || {
+        let _ = tx.send(estimate(&path, level).map_err(|e| e.to_string()));
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Text should be predicted to shrink; pseudo-random bytes should be flagged.
+    #[test]
+    fn flags_incompressible_data() {
+        let dir = std::env::temp_dir().join("freya_test_estimate");
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let text_path = dir.join("text.txt");
+        std::fs::write(
+            &text_path,
+            b"a fairly ordinary line of text\n".repeat(5_000),
+        )
+        .unwrap();
+        let text = estimate(&text_path.to_string_lossy(), CompressionLevel::Fast).unwrap();
+        assert!(!text.already_compressed());
+        assert!(text.predicted_size() < text.file_size / 10);
+
+        // xorshift keeps the test deterministic without pulling in a rand crate
+        let mut state = 0x2545_F491_4F6C_DD1Du64;
+        let noise: Vec<u8> = (0..200_000)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect();
+        let noise_path = dir.join("noise.bin");
+        std::fs::write(&noise_path, noise).unwrap();
+        let noise = estimate(&noise_path.to_string_lossy(), CompressionLevel::Fast).unwrap();
+        assert!(noise.already_compressed());
+        assert!(noise.warning().unwrap().contains("bits/byte"));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
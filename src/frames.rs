@@ -0,0 +1,322 @@
+//! Walks the frame and block headers of a zstd file without decoding anything,
+//! so we can tell where each frame starts and how big it is.
+
+use crate::seekable::{SeekEntry, SeekTable};
+use std::io::{self, Read, Seek, SeekFrom};
+
+pub const ZSTD_MAGIC: u32 = 0xFD2F_B528;
+/// Skippable frames use any magic in 0x184D2A50..=0x184D2A5F.
+pub const SKIPPABLE_MAGIC_MASK: u32 = 0xFFFF_FFF0;
+pub const SKIPPABLE_MAGIC_BASE: u32 = 0x184D_2A50;
+
+/// Fields from a zstd frame header (see RFC 8878 section 3.1.1.1).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameHeader {
+    pub window_size: u64,
+    pub content_size: Option<u64>,
+    pub has_checksum: bool,
+    pub dictionary_id: Option<u32>,
+    pub single_segment: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameKind {
+    Zstd(FrameHeader),
+    Skippable { magic: u32 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Frame {
+    pub offset: u64,
+    /// Total bytes the frame occupies on disk, headers and checksum included.
+    pub compressed_size: u64,
+    pub kind: FrameKind,
+}
+
+/// Iterates over the frames of a zstd file from the reader's current position.
+/// Block contents are skipped with seeks, so this is cheap even for huge files.
+pub struct FrameScanner<R> {
+    reader: R,
+    offset: u64,
+    done: bool,
+}
+
+impl<R: Read + Seek> FrameScanner<R> {
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let offset = reader.stream_position()?;
+        Ok(Self {
+            reader,
+            offset,
+            done: false,
+        })
+    }
+
+    fn next_frame(&mut self) -> io::Result<Option<Frame>> {
+        let mut magic = [0u8; 4];
+        let read = read_up_to(&mut self.reader, &mut magic)?;
+        if read == 0 {
+            return Ok(None);
+        }
+        if read < magic.len() {
+            return Err(truncated());
+        }
+        let magic = u32::from_le_bytes(magic);
+
+        let (kind, compressed_size) = if magic & SKIPPABLE_MAGIC_MASK == SKIPPABLE_MAGIC_BASE {
+            let mut size = [0u8; 4];
+            self.reader
+                .read_exact(&mut size)
+                .map_err(eof_is_truncated)?;
+            let size = u32::from_le_bytes(size) as u64;
+            self.skip(size)?;
+            (FrameKind::Skippable { magic }, 8 + size)
+        } else if magic == ZSTD_MAGIC {
+            let (header, header_len) = read_frame_header(&mut self.reader)?;
+            let blocks_len = self.skip_blocks()?;
+            let checksum_len = if header.has_checksum { 4 } else { 0 };
+            self.skip(checksum_len)?;
+            (
+                FrameKind::Zstd(header),
+                4 + header_len + blocks_len + checksum_len,
+            )
+        } else {
+            return Err(invalid_data(&format!(
+                "unknown frame magic {:#010x} at offset {}",
+                magic, self.offset
+            )));
+        };
+
+        let frame = Frame {
+            offset: self.offset,
+            compressed_size,
+            kind,
+        };
+        self.offset += compressed_size;
+        Ok(Some(frame))
+    }
+
+    // Returns the number of bytes taken by the blocks, headers included.
+    fn skip_blocks(&mut self) -> io::Result<u64> {
+        let mut total = 0;
+        loop {
+            let mut header = [0u8; 3];
+            self.reader
+                .read_exact(&mut header)
+                .map_err(eof_is_truncated)?;
+            let header = u32::from_le_bytes([header[0], header[1], header[2], 0]);
+            let last_block = header & 1 == 1;
+            let block_size = (header >> 3) as u64;
+            let content_len = match (header >> 1) & 0b11 {
+                0 | 2 => block_size, // raw and compressed blocks store block_size bytes
+                1 => 1,              // RLE: a single byte repeated block_size times
+                _ => return Err(invalid_data("reserved block type")),
+            };
+            self.skip(content_len)?;
+            total += 3 + content_len;
+            if last_block {
+                return Ok(total);
+            }
+        }
+    }
+
+    // Seeking past EOF succeeds silently, so check we actually landed inside the file.
+    fn skip(&mut self, len: u64) -> io::Result<()> {
+        if len == 0 {
+            return Ok(());
+        }
+        self.reader.seek(SeekFrom::Current(len as i64 - 1))?;
+        let mut last = [0u8; 1];
+        self.reader.read_exact(&mut last).map_err(eof_is_truncated)
+    }
+}
+
+impl<R: Read + Seek> Iterator for FrameScanner<R> {
+    type Item = io::Result<Frame>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let next = self.next_frame().transpose();
+        if !matches!(next, Some(Ok(_))) {
+            self.done = true;
+        }
+        next
+    }
+}
+
+/// Reads a frame header, starting right after the magic number.
+/// Returns the header and how many bytes it took.
+pub fn read_frame_header<R: Read>(reader: &mut R) -> io::Result<(FrameHeader, u64)> {
+    let mut descriptor = [0u8; 1];
+    reader
+        .read_exact(&mut descriptor)
+        .map_err(eof_is_truncated)?;
+    let descriptor = descriptor[0];
+    if descriptor & 0b0000_1000 != 0 {
+        return Err(invalid_data("frame header has the reserved bit set"));
+    }
+
+    let single_segment = descriptor & 0b0010_0000 != 0;
+    let has_checksum = descriptor & 0b0000_0100 != 0;
+    let dictionary_id_len = [0, 1, 2, 4][(descriptor & 0b11) as usize];
+    let content_size_len = match descriptor >> 6 {
+        0 if single_segment => 1,
+        0 => 0,
+        1 => 2,
+        2 => 4,
+        _ => 8,
+    };
+    let window_descriptor_len = if single_segment { 0 } else { 1 };
+
+    let rest_len = window_descriptor_len + dictionary_id_len + content_size_len;
+    let mut rest = [0u8; 13];
+    reader
+        .read_exact(&mut rest[..rest_len])
+        .map_err(eof_is_truncated)?;
+
+    let mut window_size = 0;
+    if !single_segment {
+        let exponent = (rest[0] >> 3) as u32;
+        let mantissa = (rest[0] & 0b111) as u64;
+        let window_base = 1u64 << (10 + exponent);
+        window_size = window_base + (window_base / 8) * mantissa;
+    }
+
+    let dictionary_id = match dictionary_id_len {
+        0 => None,
+        len => Some(le_uint(&rest[window_descriptor_len..window_descriptor_len + len]) as u32),
+    }
+    .filter(|&id| id != 0);
+
+    let content_size = match content_size_len {
+        0 => None,
+        // The two-byte form is stored with an offset of 256
+        2 => Some(le_uint(&rest[rest_len - 2..rest_len]) + 256),
+        len => Some(le_uint(&rest[rest_len - len..rest_len])),
+    };
+    if single_segment {
+        window_size = content_size.unwrap_or(0);
+    }
+
+    Ok((
+        FrameHeader {
+            window_size,
+            content_size,
+            has_checksum,
+            dictionary_id,
+            single_segment,
+        },
+        1 + rest_len as u64,
+    ))
+}
+
+/// Lays out the zstd frames of a file so they can be decoded independently.
+/// Uses the seek table when there is one, otherwise scans the frame headers.
+/// Returns `None` when any frame doesn't declare its decompressed size, since
+/// we then can't tell where its output goes until the frames before it are decoded.
+pub fn independent_frames<R: Read + Seek>(mut reader: R) -> io::Result<Option<Vec<SeekEntry>>> {
+    match SeekTable::read_from(&mut reader) {
+        Ok(table) => return Ok(Some(table.entries)),
+        Err(e) if e.kind() == io::ErrorKind::InvalidData => {}
+        Err(e) => return Err(e),
+    }
+
+    reader.seek(SeekFrom::Start(0))?;
+    let mut entries = Vec::new();
+    let mut decompressed_offset = 0;
+    for frame in FrameScanner::new(reader)? {
+        let frame = frame?;
+        let FrameKind::Zstd(header) = frame.kind else {
+            continue;
+        };
+        let Some(content_size) = header.content_size else {
+            return Ok(None);
+        };
+        let (Ok(compressed_size), Ok(decompressed_size)) = (
+            u32::try_from(frame.compressed_size),
+            u32::try_from(content_size),
+        ) else {
+            return Ok(None);
+        };
+        entries.push(SeekEntry {
+            compressed_offset: frame.offset,
+            decompressed_offset,
+            compressed_size,
+            decompressed_size,
+        });
+        decompressed_offset += content_size;
+    }
+    Ok(Some(entries))
+}
+
+fn le_uint(bytes: &[u8]) -> u64 {
+    bytes.iter().rev().fold(0, |acc, &b| (acc << 8) | b as u64)
+}
+
+// Like read_exact, but returns how many bytes were read if EOF came first.
+fn read_up_to<R: Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..])? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+    Ok(filled)
+}
+
+fn eof_is_truncated(e: io::Error) -> io::Error {
+    if e.kind() == io::ErrorKind::UnexpectedEof {
+        truncated()
+    } else {
+        e
+    }
+}
+
+fn truncated() -> io::Error {
+    invalid_data("truncated zstd frame")
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    // Frames from the zstd bulk API carry their content size, so a concatenation
+    // of them can be laid out for parallel decoding.
+    #[test]
+    fn scans_concatenated_frames() {
+        let parts: [&[u8]; 3] = [b"first frame", &[7u8; 100_000], b""];
+        let mut file = Vec::new();
+        for part in parts {
+            file.extend(zstd::bulk::compress(part, 3).unwrap());
+        }
+
+        let frames: Vec<Frame> = FrameScanner::new(Cursor::new(&file))
+            .unwrap()
+            .collect::<io::Result<_>>()
+            .unwrap();
+        assert_eq!(frames.len(), 3);
+        assert_eq!(
+            frames.iter().map(|f| f.compressed_size).sum::<u64>(),
+            file.len() as u64
+        );
+
+        let entries = independent_frames(Cursor::new(&file)).unwrap().unwrap();
+        let sizes: Vec<u32> = entries.iter().map(|e| e.decompressed_size).collect();
+        assert_eq!(sizes, [11, 100_000, 0]);
+        assert_eq!(entries[2].decompressed_offset, 100_011);
+
+        let truncated = &file[..file.len() - 10];
+        assert!(
+            FrameScanner::new(Cursor::new(truncated))
+                .unwrap()
+                .any(|f| f.is_err())
+        );
+    }
+}
@@ -0,0 +1,266 @@
+//! Every key binding, grouped by the context it works in. The `?` overlay lists
+//! them all and the footer shows the ones that matter right now, with the keys
+//! taken from the keymap so rebinding shows up here too.
+
+use crate::keymap::{Action, Keymap};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Keys {
+    /// Whatever the keymap binds to these actions, shown as `a/b`.
+    Actions(&'static [Action]),
+    /// Fixed keys, for typing into a text field.
+    Typed(&'static str),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Binding {
+    pub keys: Keys,
+    pub action: &'static str,
+}
+
+impl Binding {
+    /// `full` lists every key bound to each action; otherwise only the first.
+    pub fn keys_label(&self, keymap: &Keymap, full: bool) -> String {
+        let labels: Vec<String> = self
+            .key_labels(keymap, full)
+            .into_iter()
+            .map(|(label, _)| label)
+            .collect();
+        labels.join("/")
+    }
+
+    /// The parts of `keys_label` with the action each one stands for, so a
+    /// click on a key does what pressing it does.
+    pub fn key_labels(&self, keymap: &Keymap, full: bool) -> Vec<(String, Option<Action>)> {
+        match self.keys {
+            Keys::Actions(actions) => actions
+                .iter()
+                .map(|&action| {
+                    let label = match (full, keymap.key_for(action)) {
+                        (true, _) => keymap.keys_for(action),
+                        (false, Some(key)) => key,
+                        (false, None) => "unbound".to_string(),
+                    };
+                    (label, Some(action))
+                })
+                .collect(),
+            Keys::Typed(keys) => vec![(keys.to_string(), None)],
+        }
+    }
+
+    /// What clicking the binding's name does: its first action.
+    pub fn first_action(&self) -> Option<Action> {
+        match self.keys {
+            Keys::Actions(actions) => actions.first().copied(),
+            Keys::Typed(_) => None,
+        }
+    }
+}
+
+// `&[Binding { .. }, ..]` without the noise; the slice is promoted to 'static.
+// Keys are either `[Action, ..]` names or a literal for typed keys.
+macro_rules! bindings {
+    ($(($keys:tt, $action:literal)),* $(,)?) => {
+        &[$(Binding { keys: keys!($keys), action: $action }),*]
+    };
+}
+
+macro_rules! keys {
+    ([$($action:ident),*]) => {
+        Keys::Actions(&[$(Action::$action),*])
+    };
+    ($typed:literal) => {
+        Keys::Typed($typed)
+    };
+}
+
+/// What the keyboard is currently talking to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Context {
+    Main,
+    /// A file is picked and waits for Enter.
+    Pending,
+    /// A job is running.
+    Running,
+    Info,
+    Bench,
+    Settings,
+    /// Typing into a text setting.
+    EditSetting,
+    History,
+    /// Typing the history filter.
+    FilterHistory,
+    /// The help overlay itself.
+    Help,
+}
+
+impl Context {
+    /// Order of the groups in the help overlay.
+    pub const ALL: [Context; 9] = [
+        Context::Main,
+        Context::Pending,
+        Context::Running,
+        Context::Info,
+        Context::Bench,
+        Context::Settings,
+        Context::EditSetting,
+        Context::History,
+        Context::FilterHistory,
+    ];
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Context::Main => "Main screen",
+            Context::Pending => "File picked, not started",
+            Context::Running => "Job running",
+            Context::Info => "Info panel",
+            Context::Bench => "Benchmark panel",
+            Context::Settings => "Settings panel",
+            Context::EditSetting => "Editing a setting",
+            Context::History => "History panel",
+            Context::FilterHistory => "Typing a history filter",
+            Context::Help => "Help",
+        }
+    }
+
+    /// Every key that does something in this context.
+    pub fn bindings(self) -> &'static [Binding] {
+        match self {
+            Context::Main => bindings![
+                ([OpenFile], "Open File"),
+                ([OpenFolder], "Folder"),
+                ([Decompress], "Decompress"),
+                ([Info], "Info"),
+                ([Benchmark], "Benchmark"),
+                ([Up, Down], "Level"),
+                ([ToggleRemoveSource], "Remove source on/off"),
+                ([History], "History"),
+                ([Settings], "Settings"),
+                ([Help], "Help"),
+                ([Quit], "Quit"),
+            ],
+            Context::Pending => bindings![
+                ([Confirm], "Compress"),
+                ([Cancel], "Cancel"),
+                ([Up, Down], "Level (estimates again)"),
+                ([ToggleRemoveSource], "Remove source on/off"),
+            ],
+            Context::Running => bindings![
+                ([Stop], "Stop the job"),
+                ([Info], "Info"),
+                ([Benchmark], "Benchmark"),
+                ([History], "History"),
+                ([Settings], "Settings"),
+                ([Help], "Help"),
+                ([Quit], "Quit (abandons the job)"),
+            ],
+            Context::Info => bindings![([Cancel], "Close")],
+            Context::Bench => bindings![
+                ([Up, Down], "Pick"),
+                ([Confirm], "Use level"),
+                ([Cancel], "Close"),
+            ],
+            Context::Settings => bindings![
+                ([Up, Down], "Pick"),
+                ([Left, Right], "Change"),
+                ([Confirm], "Edit"),
+                ([Settings, Cancel], "Close"),
+            ],
+            Context::EditSetting => bindings![
+                ("Enter", "Save"),
+                ("Esc", "Cancel"),
+                ("Backspace", "Delete a character"),
+            ],
+            Context::History => bindings![
+                ([Up, Down], "Scroll"),
+                ([Left, Right], "Sort"),
+                ([Filter], "Filter"),
+                ([History, Cancel], "Close"),
+            ],
+            Context::FilterHistory => bindings![
+                ("Enter", "Keep filter"),
+                ("Esc", "Clear"),
+                ("Backspace", "Delete a character"),
+            ],
+            Context::Help => bindings![([Help, Cancel], "Close")],
+        }
+    }
+
+    /// The short list for the footer. Panels show their own keys at the bottom
+    /// of the panel, so while one is open the footer lists what still works around it.
+    pub fn hints(self) -> &'static [Binding] {
+        match self {
+            Context::Main => bindings![
+                ([OpenFile], "Open File"),
+                ([OpenFolder], "Folder"),
+                ([Decompress], "Decompress"),
+                ([Up, Down], "Level"),
+                ([History], "History"),
+                ([Settings], "Settings"),
+                ([Help], "Help"),
+                ([Quit], "Quit"),
+            ],
+            Context::Pending => bindings![
+                ([Confirm], "Compress"),
+                ([Cancel], "Cancel"),
+                ([Up, Down], "Level"),
+                ([ToggleRemoveSource], "Remove source"),
+                ([Help], "Help"),
+                ([Quit], "Quit"),
+            ],
+            Context::Running => bindings![
+                ([Stop], "Stop"),
+                ([Info], "Info"),
+                ([History], "History"),
+                ([Help], "Help"),
+                ([Quit], "Quit"),
+            ],
+            Context::Info | Context::Bench | Context::Settings | Context::History => bindings![
+                ([Cancel], "Close panel"),
+                ([OpenFile], "Open File"),
+                ([OpenFolder], "Folder"),
+                ([Decompress], "Decompress"),
+                ([Help], "Help"),
+                ([Quit], "Quit"),
+            ],
+            Context::EditSetting | Context::FilterHistory => {
+                bindings![("Enter/Esc", "Finish typing")]
+            }
+            Context::Help => bindings![([Help, Cancel], "Close help"), ([Quit], "Quit")],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bindings_are_unique_and_follow_the_keymap() {
+        for context in Context::ALL {
+            let bindings = context.bindings();
+            for (index, binding) in bindings.iter().enumerate() {
+                assert!(
+                    !bindings[index + 1..].iter().any(|b| b.keys == binding.keys),
+                    "{} lists {:?} twice",
+                    context.title(),
+                    binding.keys
+                );
+            }
+        }
+        // Nothing that starts a new job is offered while one runs
+        let starts_job = |binding: &Binding| {
+            matches!(binding.keys, Keys::Actions(actions)
+                if actions.iter().any(|a| matches!(a, Action::OpenFile | Action::OpenFolder | Action::Decompress)))
+        };
+        assert!(!Context::Running.hints().iter().any(starts_job));
+
+        let keymap = Keymap::default();
+        let level = Context::Main.hints()[3];
+        assert_eq!(level.keys_label(&keymap, false), "↑/↓");
+        assert_eq!(level.keys_label(&keymap, true), "↑ k/↓ j");
+        let config = [("up".to_string(), "ctrl+p".to_string())].into();
+        let keymap = Keymap::from_config(&config).unwrap();
+        assert_eq!(level.keys_label(&keymap, false), "Ctrl+p/↓");
+    }
+}
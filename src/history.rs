@@ -0,0 +1,427 @@
+//! A record of every job, kept across sessions as JSON lines in
+//! `history.jsonl` under the XDG data dir (`~/.local/share/freya` on Linux).
+
+use crate::CompressionLevel;
+use crate::batch::BatchReport;
+use serde::{Deserialize, Serialize};
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Points Freya at a different history file.
+pub const HISTORY_ENV: &str = "FREYA_HISTORY";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobKind {
+    Compress,
+    Decompress,
+}
+
+impl JobKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            JobKind::Compress => "compress",
+            JobKind::Decompress => "decompress",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// Unix time in seconds.
+    pub finished_at: u64,
+    pub kind: JobKind,
+    pub input: PathBuf,
+    pub output: Option<PathBuf>,
+    pub input_size: u64,
+    pub output_size: u64,
+    /// Compressed size as a percentage of the uncompressed size, in both directions.
+    pub ratio: f64,
+    pub duration_ms: u64,
+    /// Not recorded for decompression.
+    pub level: Option<CompressionLevel>,
+    /// Set when the job failed.
+    pub error: Option<String>,
+}
+
+impl HistoryEntry {
+    pub fn succeeded(&self) -> bool {
+        self.error.is_none()
+    }
+
+    pub fn outcome(&self) -> &str {
+        self.error.as_deref().unwrap_or("ok")
+    }
+
+    /// Entries for every file a batch compressed, stored or failed on. Skipped files
+    /// weren't jobs, so they're left out.
+    pub fn from_batch(report: &BatchReport, level: CompressionLevel) -> Vec<HistoryEntry> {
+        let finished_at = unix_now();
+        let written = report
+            .compressed
+            .iter()
+            .chain(&report.stored)
+            .map(|e| HistoryEntry {
+                finished_at,
+                kind: JobKind::Compress,
+                input: e.path.clone(),
+                output: Some(e.output_path.clone()),
+                input_size: e.original_size,
+                output_size: e.compressed_size,
+                ratio: percent(e.compressed_size, e.original_size),
+                duration_ms: e.duration.as_millis() as u64,
+                level: Some(level),
+                error: None,
+            });
+        let failed = report.failed.iter().map(|(path, error)| HistoryEntry {
+            finished_at,
+            kind: JobKind::Compress,
+            input: path.clone(),
+            output: None,
+            input_size: 0,
+            output_size: 0,
+            ratio: 0.0,
+            duration_ms: 0,
+            level: Some(level),
+            error: Some(error.clone()),
+        });
+        written.chain(failed).collect()
+    }
+
+    fn matches(&self, filter: &str) -> bool {
+        let filter = filter.to_lowercase();
+        let level = self.level.map_or("", |l| l.label());
+        [
+            self.input.to_string_lossy().as_ref(),
+            self.output
+                .as_deref()
+                .map(Path::to_string_lossy)
+                .unwrap_or_default()
+                .as_ref(),
+            self.kind.label(),
+            level,
+            self.outcome(),
+        ]
+        .iter()
+        .any(|field| field.to_lowercase().contains(&filter))
+    }
+}
+
+/// A job that has started, turned into a `HistoryEntry` once it ends.
+#[derive(Debug, Clone)]
+pub struct JobStart {
+    pub kind: JobKind,
+    pub input: PathBuf,
+    pub output: Option<PathBuf>,
+    pub level: Option<CompressionLevel>,
+    pub started: Instant,
+}
+
+impl JobStart {
+    pub fn new(
+        kind: JobKind,
+        input: PathBuf,
+        output: Option<PathBuf>,
+        level: Option<CompressionLevel>,
+    ) -> Self {
+        Self {
+            kind,
+            input,
+            output,
+            level,
+            started: Instant::now(),
+        }
+    }
+
+    pub fn finished(&self, input_size: u64, output_size: u64) -> HistoryEntry {
+        let ratio = match self.kind {
+            JobKind::Compress => percent(output_size, input_size),
+            JobKind::Decompress => percent(input_size, output_size),
+        };
+        self.entry(input_size, output_size, ratio, None)
+    }
+
+    pub fn failed(&self, error: &str) -> HistoryEntry {
+        self.entry(0, 0, 0.0, Some(error.to_string()))
+    }
+
+    fn entry(
+        &self,
+        input_size: u64,
+        output_size: u64,
+        ratio: f64,
+        error: Option<String>,
+    ) -> HistoryEntry {
+        HistoryEntry {
+            finished_at: unix_now(),
+            kind: self.kind,
+            input: self.input.clone(),
+            output: self.output.clone(),
+            input_size,
+            output_size,
+            ratio,
+            duration_ms: self.started.elapsed().as_millis() as u64,
+            level: self.level,
+            error,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortKey {
+    /// Newest first.
+    #[default]
+    Date,
+    /// Best ratio (smallest output) first, failed jobs last.
+    Ratio,
+    /// Largest input first.
+    Size,
+    /// Slowest first.
+    Duration,
+}
+
+impl SortKey {
+    pub const ALL: [SortKey; 4] = [
+        SortKey::Date,
+        SortKey::Ratio,
+        SortKey::Size,
+        SortKey::Duration,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SortKey::Date => "date",
+            SortKey::Ratio => "ratio",
+            SortKey::Size => "size",
+            SortKey::Duration => "duration",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|&k| k == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    pub fn previous(self) -> Self {
+        let index = Self::ALL.iter().position(|&k| k == self).unwrap_or(0);
+        Self::ALL[(index + Self::ALL.len() - 1) % Self::ALL.len()]
+    }
+}
+
+impl std::str::FromStr for SortKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        SortKey::ALL
+            .into_iter()
+            .find(|key| key.label().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                format!(
+                    "unknown sort key '{}' (expected date, ratio, size or duration)",
+                    s
+                )
+            })
+    }
+}
+
+/// Entries containing `filter` (case-insensitive, in paths, kind, level or outcome),
+/// ordered by `sort`.
+pub fn select<'a>(
+    entries: &'a [HistoryEntry],
+    filter: &str,
+    sort: SortKey,
+) -> Vec<&'a HistoryEntry> {
+    let mut selected: Vec<&HistoryEntry> = entries.iter().filter(|e| e.matches(filter)).collect();
+    match sort {
+        // Stable sort on reversed input keeps same-second batch entries in order
+        SortKey::Date => {
+            selected.reverse();
+            selected.sort_by_key(|e| std::cmp::Reverse(e.finished_at));
+        }
+        // Failed jobs have no ratio; keep them out of the way at the end
+        SortKey::Ratio => selected.sort_by(|a, b| {
+            (!a.succeeded())
+                .cmp(&!b.succeeded())
+                .then(a.ratio.total_cmp(&b.ratio))
+        }),
+        SortKey::Size => selected.sort_by_key(|e| std::cmp::Reverse(e.input_size)),
+        SortKey::Duration => selected.sort_by_key(|e| std::cmp::Reverse(e.duration_ms)),
+    }
+    selected
+}
+
+/// State of the history panel.
+#[derive(Debug, Default)]
+pub struct HistoryView {
+    /// Everything loaded from the history file, oldest first.
+    pub entries: Vec<HistoryEntry>,
+    pub sort: SortKey,
+    pub filter: String,
+    /// The filter is being typed; keys go to it until Enter or Esc.
+    pub filtering: bool,
+    /// Row highlighted among the visible entries.
+    pub selected: usize,
+}
+
+impl HistoryView {
+    pub fn new(entries: Vec<HistoryEntry>) -> Self {
+        Self {
+            entries,
+            ..Self::default()
+        }
+    }
+
+    /// Entries passing the filter, in display order.
+    pub fn visible(&self) -> Vec<&HistoryEntry> {
+        select(&self.entries, &self.filter, self.sort)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct History {
+    path: PathBuf,
+}
+
+impl History {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// `$FREYA_HISTORY`, or `freya/history.jsonl` under the platform data dir.
+    pub fn open_default() -> Option<Self> {
+        match std::env::var_os(HISTORY_ENV) {
+            Some(path) => Some(Self::new(PathBuf::from(path))),
+            None => dirs::data_dir().map(|dir| Self::new(dir.join("freya").join("history.jsonl"))),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn append(&self, entries: &[HistoryEntry]) -> io::Result<()> {
+        if entries.is_empty() {
+            return Ok(());
+        }
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        // One write per batch of lines, so concurrent sessions don't interleave halves
+        let mut lines = Vec::new();
+        for entry in entries {
+            serde_json::to_writer(&mut lines, entry)?;
+            lines.push(b'\n');
+        }
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?
+            .write_all(&lines)
+    }
+
+    /// Every entry, oldest first. Lines that don't parse (say, cut short by a
+    /// crash) are skipped rather than hiding the rest of the history.
+    pub fn load(&self) -> io::Result<Vec<HistoryEntry>> {
+        let file = match std::fs::File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        let mut entries = Vec::new();
+        for line in io::BufReader::new(file).lines() {
+            if let Ok(entry) = serde_json::from_str(&line?) {
+                entries.push(entry);
+            }
+        }
+        Ok(entries)
+    }
+}
+
+fn percent(part: u64, whole: u64) -> f64 {
+    if whole == 0 {
+        return 0.0;
+    }
+    part as f64 / whole as f64 * 100.0
+}
+
+pub(crate) fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// Formats a Unix timestamp as `YYYY-MM-DD HH:MM` (UTC).
+pub fn format_time(secs: u64) -> String {
+    let date = crate::output::date_from_unix(secs);
+    let minutes = secs % 86_400 / 60;
+    format!("{} {:02}:{:02}", date, minutes / 60, minutes % 60)
+}
+
+pub fn format_duration(ms: u64) -> String {
+    let duration = Duration::from_millis(ms);
+    if duration.as_secs() >= 60 {
+        format!(
+            "{}m{:02}s",
+            duration.as_secs() / 60,
+            duration.as_secs() % 60
+        )
+    } else {
+        format!("{:.1}s", duration.as_secs_f64())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn appends_loads_sorts_and_filters() {
+        let dir = std::env::temp_dir().join("freya_test_history");
+        let _ = std::fs::remove_dir_all(&dir);
+        let history = History::new(dir.join("nested").join("history.jsonl"));
+        assert!(history.load().unwrap().is_empty());
+
+        let job = JobStart::new(
+            JobKind::Compress,
+            PathBuf::from("/data/big.log"),
+            Some(PathBuf::from("/data/big.log.zst")),
+            Some(CompressionLevel::Best),
+        );
+        let mut big = job.finished(1000, 100);
+        big.finished_at = 10;
+        let mut failed = JobStart::new(
+            JobKind::Decompress,
+            PathBuf::from("/data/broken.zst"),
+            None,
+            None,
+        )
+        .failed("Unknown frame descriptor");
+        failed.finished_at = 20;
+        let mut small = job.finished(10, 5);
+        small.finished_at = 30;
+        history.append(&[big.clone(), failed.clone()]).unwrap();
+        history.append(std::slice::from_ref(&small)).unwrap();
+
+        // A torn last line must not hide the entries before it
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(history.path())
+            .unwrap()
+            .write_all(b"{\"finished_at\": 4")
+            .unwrap();
+        let entries = history.load().unwrap();
+        assert_eq!(entries, vec![big.clone(), failed.clone(), small.clone()]);
+        assert_eq!(big.ratio, 10.0);
+
+        let by_date = select(&entries, "", SortKey::Date);
+        assert_eq!(by_date, vec![&small, &failed, &big]);
+        let by_ratio = select(&entries, "", SortKey::Ratio);
+        assert_eq!(by_ratio, vec![&big, &small, &failed]);
+        assert_eq!(select(&entries, "FRAME", SortKey::Date), vec![&failed]);
+        assert_eq!(select(&entries, "best", SortKey::Size), vec![&big, &small]);
+
+        assert_eq!(format_time(86_400 + 3_660), "1970-01-02 01:01");
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,310 @@
+//! Reports what's inside a compressed file without decompressing it, in the
+//! spirit of `zstd -lv`. Used by `freya info` and the TUI info panel.
+
+use crate::frames::{Frame, FrameHeader, FrameKind, FrameScanner};
+use crate::human_bytes;
+use crate::seekable::{SEEKABLE_MAGIC, SKIPPABLE_MAGIC, SeekTable};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use zstd::stream::Decoder;
+
+/// How much compressed input to decode when estimating the size of frames
+/// that don't record it.
+const ESTIMATE_SAMPLE: u64 = 1024 * 1024;
+
+#[derive(Debug, Clone)]
+pub struct FileInfo {
+    pub path: PathBuf,
+    pub file_size: u64,
+    pub frames: Vec<Frame>,
+    pub seek_table: Option<SeekTable>,
+    /// Decompressed size extrapolated from a sample, when some frame doesn't record its size.
+    pub estimated_content_size: Option<u64>,
+}
+
+impl FileInfo {
+    pub fn read(path: &Path) -> io::Result<Self> {
+        Self::from_reader(path.to_path_buf(), File::open(path)?)
+    }
+
+    /// The same report for a file already open (or in memory); `path` is only
+    /// shown, never opened.
+    pub fn from_reader<R: Read + Seek>(path: PathBuf, mut file: R) -> io::Result<Self> {
+        let file_size = file.seek(SeekFrom::End(0))?;
+
+        let seek_table = match SeekTable::read_from(&mut file) {
+            Ok(table) => Some(table),
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => None,
+            Err(e) => return Err(e),
+        };
+        file.seek(SeekFrom::Start(0))?;
+        let frames = FrameScanner::new(&mut file)?.collect::<io::Result<Vec<_>>>()?;
+
+        let mut info = FileInfo {
+            path,
+            file_size,
+            frames,
+            seek_table,
+            estimated_content_size: None,
+        };
+        if info.content_size().is_none() {
+            file.seek(SeekFrom::Start(0))?;
+            info.estimated_content_size = estimate_content_size(&mut file, file_size)?;
+        }
+        Ok(info)
+    }
+
+    pub fn zstd_frames(&self) -> impl Iterator<Item = &FrameHeader> {
+        self.frames.iter().filter_map(|f| match &f.kind {
+            FrameKind::Zstd(header) => Some(header),
+            FrameKind::Skippable { .. } => None,
+        })
+    }
+
+    pub fn skippable_frames(&self) -> usize {
+        self.frames.len() - self.zstd_frames().count()
+    }
+
+    /// Exact decompressed size, if the seek table or every frame header records it.
+    pub fn content_size(&self) -> Option<u64> {
+        if let Some(table) = &self.seek_table {
+            return Some(table.decompressed_len());
+        }
+        // Headers can claim anything; a total past u64 is as unknown as a missing one
+        self.zstd_frames()
+            .try_fold(0u64, |total, h| total.checked_add(h.content_size?))
+    }
+
+    /// Compressed size as a percentage of the original, and whether it's estimated.
+    pub fn ratio(&self) -> Option<(f64, bool)> {
+        let (content_size, estimated) = match self.content_size() {
+            Some(size) => (size, false),
+            None => (self.estimated_content_size?, true),
+        };
+        (content_size > 0).then(|| {
+            (
+                self.file_size as f64 / content_size as f64 * 100.0,
+                estimated,
+            )
+        })
+    }
+
+    /// One line per frame, like the table printed by `zstd -lv`.
+    pub fn frame_lines(&self) -> Vec<String> {
+        let mut lines = vec![format!(
+            "{:>5} {:>12} {:>12} {:>12} {:>10} {:>5} {:>10}",
+            "#", "Offset", "Compressed", "Content", "Window", "Check", "Dict"
+        )];
+        for (index, frame) in self.frames.iter().enumerate() {
+            let line = match &frame.kind {
+                FrameKind::Zstd(header) => format!(
+                    "{:>5} {:>12} {:>12} {:>12} {:>10} {:>5} {:>10}",
+                    index,
+                    frame.offset,
+                    frame.compressed_size,
+                    header
+                        .content_size
+                        .map_or("?".to_string(), |size| size.to_string()),
+                    human_bytes(header.window_size),
+                    if header.has_checksum { "yes" } else { "no" },
+                    header
+                        .dictionary_id
+                        .map_or("-".to_string(), |id| id.to_string()),
+                ),
+                FrameKind::Skippable { magic } => format!(
+                    "{:>5} {:>12} {:>12}   skippable {:#010x}{}",
+                    index,
+                    frame.offset,
+                    frame.compressed_size,
+                    magic,
+                    if self.is_seek_table(frame) {
+                        " (seek table)"
+                    } else {
+                        ""
+                    },
+                ),
+            };
+            lines.push(line);
+        }
+        lines
+    }
+
+    fn is_seek_table(&self, frame: &Frame) -> bool {
+        self.seek_table.is_some()
+            && frame.kind
+                == FrameKind::Skippable {
+                    magic: SKIPPABLE_MAGIC,
+                }
+            && frame.offset + frame.compressed_size == self.file_size
+    }
+
+    fn format_name(&self) -> String {
+        match &self.seek_table {
+            Some(table) => {
+                let frame_size = table.entries.first().map_or(0, |e| e.decompressed_size);
+                format!(
+                    "zstd seekable ({} frames of {}, magic {:#010x})",
+                    table.entries.len(),
+                    human_bytes(frame_size as u64),
+                    SEEKABLE_MAGIC
+                )
+            }
+            None => "zstd".to_string(),
+        }
+    }
+}
+
+impl fmt::Display for FileInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let headers: Vec<&FrameHeader> = self.zstd_frames().collect();
+        writeln!(f, "File:          {}", self.path.display())?;
+        writeln!(f, "Format:        {}", self.format_name())?;
+        writeln!(
+            f,
+            "Frames:        {} zstd, {} skippable",
+            headers.len(),
+            self.skippable_frames()
+        )?;
+        writeln!(
+            f,
+            "Compressed:    {} ({} bytes)",
+            human_bytes(self.file_size),
+            self.file_size
+        )?;
+        match (self.content_size(), self.estimated_content_size) {
+            (Some(size), _) => {
+                writeln!(f, "Decompressed:  {} ({} bytes)", human_bytes(size), size)?
+            }
+            (None, Some(size)) => writeln!(f, "Decompressed:  ~{} (estimated)", human_bytes(size))?,
+            (None, None) => writeln!(f, "Decompressed:  unknown")?,
+        }
+        match self.ratio() {
+            Some((ratio, false)) => writeln!(f, "Ratio:         {:.2}% of original", ratio)?,
+            Some((ratio, true)) => writeln!(f, "Ratio:         ~{:.2}% of original", ratio)?,
+            None => writeln!(f, "Ratio:         unknown")?,
+        }
+
+        let window = headers.iter().map(|h| h.window_size).max().unwrap_or(0);
+        writeln!(f, "Window size:   {}", human_bytes(window))?;
+
+        let with_checksum = headers.iter().filter(|h| h.has_checksum).count();
+        let checksum = match with_checksum {
+            0 => "none".to_string(),
+            n if n == headers.len() => "XXH64 (all frames)".to_string(),
+            n => format!("XXH64 ({} of {} frames)", n, headers.len()),
+        };
+        writeln!(f, "Checksum:      {}", checksum)?;
+
+        let mut dictionaries: Vec<u32> = headers.iter().filter_map(|h| h.dictionary_id).collect();
+        dictionaries.sort_unstable();
+        dictionaries.dedup();
+        let dictionaries = if dictionaries.is_empty() {
+            "none".to_string()
+        } else {
+            dictionaries
+                .iter()
+                .map(u32::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        write!(f, "Dictionary ID: {}", dictionaries)
+    }
+}
+
+// Decodes the first ESTIMATE_SAMPLE compressed bytes and scales the output up
+// by how much of the file that covered.
+fn estimate_content_size<R: Read>(reader: R, file_size: u64) -> io::Result<Option<u64>> {
+    let mut counted = CountingReader {
+        inner: BufReader::new(reader.take(ESTIMATE_SAMPLE)),
+        count: 0,
+    };
+    let mut decoder = match Decoder::with_buffer(&mut counted) {
+        Ok(decoder) => decoder,
+        Err(_) => return Ok(None),
+    };
+    let mut buffer = vec![0u8; 64 * 1024];
+    let mut decoded = 0u64;
+    // Running out of sample mid-frame shows up as an error; keep what we decoded so far
+    while let Ok(read @ 1..) = decoder.read(&mut buffer) {
+        decoded += read as u64;
+    }
+    drop(decoder);
+    if decoded == 0 || counted.count == 0 {
+        return Ok(None);
+    }
+    Ok(Some(
+        (decoded as f64 * file_size as f64 / counted.count as f64) as u64,
+    ))
+}
+
+struct CountingReader<R> {
+    inner: R,
+    count: u64,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.count += read as u64;
+        Ok(read)
+    }
+}
+
+impl<R: io::BufRead> io::BufRead for CountingReader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.count += amount as u64;
+        self.inner.consume(amount);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    // A streaming encoder doesn't record the content size, so info falls back
+    // to an estimate; a one-shot frame records it exactly.
+    #[test]
+    fn reports_exact_and_estimated_sizes() {
+        let dir = std::env::temp_dir().join("freya_test_info");
+        std::fs::create_dir_all(&dir).unwrap();
+        let data = b"freya info panel\n".repeat(10_000);
+
+        let exact_path = dir.join("exact.zst");
+        std::fs::write(&exact_path, zstd::bulk::compress(&data, 3).unwrap()).unwrap();
+        let info = FileInfo::read(&exact_path).unwrap();
+        assert_eq!(info.content_size(), Some(data.len() as u64));
+        assert_eq!(info.zstd_frames().count(), 1);
+        assert!(!info.ratio().unwrap().1);
+
+        let streamed_path = dir.join("streamed.zst");
+        let mut encoder = zstd::stream::Encoder::new(Vec::new(), 3).unwrap();
+        encoder.include_checksum(true).unwrap();
+        encoder.write_all(&data).unwrap();
+        std::fs::write(&streamed_path, encoder.finish().unwrap()).unwrap();
+        let info = FileInfo::read(&streamed_path).unwrap();
+        assert_eq!(info.content_size(), None);
+        assert_eq!(info.estimated_content_size, Some(data.len() as u64));
+        assert!(info.to_string().contains("Checksum:      XXH64 (all frames)"));
+
+        // Two empty frames whose headers claim more than fits in a u64 together
+        let mut lying = Vec::new();
+        for _ in 0..2 {
+            lying.extend([0x28, 0xB5, 0x2F, 0xFD, 0b1100_0000, 0]);
+            lying.extend((u64::MAX / 2 + 1).to_le_bytes());
+            lying.extend([1, 0, 0]);
+        }
+        let info =
+            FileInfo::from_reader(PathBuf::from("lying.zst"), io::Cursor::new(lying)).unwrap();
+        assert_eq!(info.content_size(), None);
+        assert_eq!(info.ratio(), None);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
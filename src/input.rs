@@ -0,0 +1,188 @@
+//! Where the TUI gets its input from: terminal events, the files the user
+//! picks and the time. The app reads them through these traits, so tests can
+//! script a session with `ScriptedEvents`, `ScriptedPicker` and `FakeClock`
+//! instead of a terminal, a native dialog and the wall clock. Those are only
+//! built for tests, or for other crates with the `test-util` feature.
+
+use crossterm::event::{self, Event};
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+#[cfg(any(test, feature = "test-util"))]
+pub use scripted::{FakeClock, ScriptedEvents, ScriptedPicker};
+
+/// A name and the extensions a file dialog offers.
+pub type FileFilter = (&'static str, &'static [&'static str]);
+
+pub const ZSTD_FILES: FileFilter = ("Zstd compressed", &["zst"]);
+
+pub trait EventSource: fmt::Debug {
+    /// The next event, or `None` if none came within `timeout`.
+    fn next(&mut self, timeout: Duration) -> io::Result<Option<Event>>;
+}
+
+pub trait FilePicker: fmt::Debug {
+    /// `None` when the user cancels.
+    fn pick_file(&mut self, filter: Option<FileFilter>) -> Option<PathBuf>;
+    fn pick_folder(&mut self) -> Option<PathBuf>;
+}
+
+pub trait Clock: fmt::Debug {
+    fn now(&self) -> Instant;
+}
+
+/// Events from the terminal, through crossterm.
+#[derive(Debug, Default)]
+pub struct Terminal;
+
+impl EventSource for Terminal {
+    fn next(&mut self, timeout: Duration) -> io::Result<Option<Event>> {
+        if event::poll(timeout)? {
+            event::read().map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// The operating system's file dialogs, through rfd.
+#[derive(Debug, Default)]
+pub struct NativePicker;
+
+impl FilePicker for NativePicker {
+    fn pick_file(&mut self, filter: Option<FileFilter>) -> Option<PathBuf> {
+        let dialog = rfd::FileDialog::new();
+        match filter {
+            Some((name, extensions)) => dialog.add_filter(name, extensions).pick_file(),
+            None => dialog.pick_file(),
+        }
+    }
+
+    fn pick_folder(&mut self) -> Option<PathBuf> {
+        rfd::FileDialog::new().pick_folder()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+#[cfg(any(test, feature = "test-util"))]
+mod scripted {
+    use super::*;
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use std::cell::{Cell, RefCell};
+    use std::collections::VecDeque;
+    use std::rc::Rc;
+
+    /// A clock that only moves when told to. Clones share the same time.
+    #[derive(Debug, Clone)]
+    pub struct FakeClock {
+        start: Instant,
+        elapsed: Rc<Cell<Duration>>,
+    }
+
+    impl Default for FakeClock {
+        fn default() -> Self {
+            Self {
+                start: Instant::now(),
+                elapsed: Rc::default(),
+            }
+        }
+    }
+
+    impl FakeClock {
+        pub fn advance(&self, by: Duration) {
+            self.elapsed.set(self.elapsed.get() + by);
+        }
+    }
+
+    impl Clock for FakeClock {
+        fn now(&self) -> Instant {
+            self.start + self.elapsed.get()
+        }
+    }
+
+    /// Hands out queued events one per call. Once they run out, every call waits
+    /// out its timeout on `clock` instead of blocking, so a loop driven by it
+    /// advances time deterministically. Clones share the same queue, so a test
+    /// can keep one to add events after giving the other to the app.
+    #[derive(Debug, Clone, Default)]
+    pub struct ScriptedEvents {
+        events: Rc<RefCell<VecDeque<Event>>>,
+        clock: FakeClock,
+    }
+
+    impl ScriptedEvents {
+        pub fn new(clock: FakeClock) -> Self {
+            Self {
+                events: Rc::default(),
+                clock,
+            }
+        }
+
+        pub fn push(&self, event: Event) -> &Self {
+            self.events.borrow_mut().push_back(event);
+            self
+        }
+
+        /// A press of `code` without modifiers.
+        pub fn key(&self, code: KeyCode) -> &Self {
+            self.push(Event::Key(KeyEvent::new(code, KeyModifiers::NONE)))
+        }
+
+        /// A press of each character of `text`, in order.
+        pub fn keys(&self, text: &str) -> &Self {
+            for c in text.chars() {
+                self.key(KeyCode::Char(c));
+            }
+            self
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.events.borrow().is_empty()
+        }
+    }
+
+    impl EventSource for ScriptedEvents {
+        fn next(&mut self, timeout: Duration) -> io::Result<Option<Event>> {
+            let event = self.events.borrow_mut().pop_front();
+            if event.is_none() {
+                self.clock.advance(timeout);
+            }
+            Ok(event)
+        }
+    }
+
+    /// Answers file dialogs from a queue; `None` entries, or an empty queue, are
+    /// the user cancelling.
+    #[derive(Debug, Default)]
+    pub struct ScriptedPicker {
+        picks: VecDeque<Option<PathBuf>>,
+    }
+
+    impl ScriptedPicker {
+        pub fn new(picks: impl IntoIterator<Item = Option<PathBuf>>) -> Self {
+            Self {
+                picks: picks.into_iter().collect(),
+            }
+        }
+    }
+
+    impl FilePicker for ScriptedPicker {
+        fn pick_file(&mut self, _filter: Option<FileFilter>) -> Option<PathBuf> {
+            self.picks.pop_front().flatten()
+        }
+
+        fn pick_folder(&mut self) -> Option<PathBuf> {
+            self.picks.pop_front().flatten()
+        }
+    }
+}
@@ -0,0 +1,421 @@
+//! What the keys do. The app reacts to `Action`s; the keymap decides which keys
+//! (with modifiers, or sequences of keys such as `g h`) produce them. Defaults
+//! can be replaced per action from the `[keys]` table of the config.
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::collections::BTreeMap;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Quit,
+    Help,
+    OpenFile,
+    OpenFolder,
+    Decompress,
+    Info,
+    Benchmark,
+    History,
+    Settings,
+    ToggleRemoveSource,
+    /// Starts typing a filter in the history panel.
+    Filter,
+    Up,
+    Down,
+    Left,
+    Right,
+    Confirm,
+    Cancel,
+    /// Stops the running job.
+    Stop,
+}
+
+impl Action {
+    pub const ALL: [Action; 18] = [
+        Action::Quit,
+        Action::Help,
+        Action::OpenFile,
+        Action::OpenFolder,
+        Action::Decompress,
+        Action::Info,
+        Action::Benchmark,
+        Action::History,
+        Action::Settings,
+        Action::ToggleRemoveSource,
+        Action::Filter,
+        Action::Up,
+        Action::Down,
+        Action::Left,
+        Action::Right,
+        Action::Confirm,
+        Action::Cancel,
+        Action::Stop,
+    ];
+
+    /// The name used in the `[keys]` config table.
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::Help => "help",
+            Action::OpenFile => "open_file",
+            Action::OpenFolder => "open_folder",
+            Action::Decompress => "decompress",
+            Action::Info => "info",
+            Action::Benchmark => "benchmark",
+            Action::History => "history",
+            Action::Settings => "settings",
+            Action::ToggleRemoveSource => "toggle_remove_source",
+            Action::Filter => "filter",
+            Action::Up => "up",
+            Action::Down => "down",
+            Action::Left => "left",
+            Action::Right => "right",
+            Action::Confirm => "confirm",
+            Action::Cancel => "cancel",
+            Action::Stop => "stop",
+        }
+    }
+
+    fn default_keys(self) -> &'static str {
+        match self {
+            Action::Quit => "q",
+            Action::Help => "?",
+            Action::OpenFile => "o",
+            Action::OpenFolder => "f",
+            Action::Decompress => "d",
+            Action::Info => "i",
+            Action::Benchmark => "b",
+            Action::History => "h",
+            Action::Settings => "s",
+            Action::ToggleRemoveSource => "r",
+            Action::Filter => "/",
+            Action::Up => "up, k",
+            Action::Down => "down, j",
+            Action::Left => "left",
+            Action::Right => "right",
+            Action::Confirm => "enter",
+            Action::Cancel => "esc",
+            Action::Stop => "x",
+        }
+    }
+}
+
+impl std::str::FromStr for Action {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Action::ALL
+            .into_iter()
+            .find(|action| action.name() == s)
+            .ok_or_else(|| format!("unknown action '{}'", s))
+    }
+}
+
+/// One key press with its modifiers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyChord {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyChord {
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        // Shift is already part of a character ('J' vs 'j'), and terminals
+        // disagree on whether they report it too
+        let modifiers = match code {
+            KeyCode::Char(_) => modifiers - KeyModifiers::SHIFT,
+            _ => modifiers,
+        };
+        Self { code, modifiers }
+    }
+
+    pub fn from_event(event: &KeyEvent) -> Self {
+        Self::new(event.code, event.modifiers)
+    }
+}
+
+impl std::str::FromStr for KeyChord {
+    type Err = String;
+
+    /// `q`, `?`, `enter`, `ctrl+c`, `alt+shift+left`, `f5`, `space`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts: Vec<&str> = s.split('+').collect();
+        let key = parts
+            .pop()
+            .filter(|key| !key.is_empty())
+            .ok_or_else(|| format!("'{}' has no key (write 'plus' for the + key)", s))?;
+        let mut modifiers = KeyModifiers::NONE;
+        for part in parts {
+            modifiers |= match part.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" | "meta" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return Err(format!("unknown modifier '{}' in '{}'", part, s)),
+            };
+        }
+
+        let mut chars = key.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match key.to_ascii_lowercase().as_str() {
+                "enter" | "return" => KeyCode::Enter,
+                "esc" | "escape" => KeyCode::Esc,
+                "tab" => KeyCode::Tab,
+                "backtab" => KeyCode::BackTab,
+                "backspace" => KeyCode::Backspace,
+                "delete" | "del" => KeyCode::Delete,
+                "insert" | "ins" => KeyCode::Insert,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "space" => KeyCode::Char(' '),
+                "plus" => KeyCode::Char('+'),
+                "comma" => KeyCode::Char(','),
+                name => match name.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                    Some(n @ 1..=24) => KeyCode::F(n),
+                    _ => return Err(format!("unknown key '{}'", key)),
+                },
+            },
+        };
+        let code = match code {
+            KeyCode::Char(c) if modifiers.contains(KeyModifiers::SHIFT) => {
+                KeyCode::Char(c.to_ascii_uppercase())
+            }
+            code => code,
+        };
+        Ok(KeyChord::new(code, modifiers))
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (modifier, name) in [
+            (KeyModifiers::CONTROL, "Ctrl+"),
+            (KeyModifiers::ALT, "Alt+"),
+            (KeyModifiers::SHIFT, "Shift+"),
+        ] {
+            if self.modifiers.contains(modifier) {
+                f.write_str(name)?;
+            }
+        }
+        match self.code {
+            KeyCode::Up => f.write_str("↑"),
+            KeyCode::Down => f.write_str("↓"),
+            KeyCode::Left => f.write_str("←"),
+            KeyCode::Right => f.write_str("→"),
+            KeyCode::Char(' ') => f.write_str("Space"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::F(n) => write!(f, "F{}", n),
+            code => write!(f, "{}", code),
+        }
+    }
+}
+
+/// Keys pressed one after the other, like `g h`.
+pub type KeySequence = Vec<KeyChord>;
+
+/// Parses `"ctrl+q, g q"`: alternatives separated by commas, each a
+/// space-separated sequence. An empty string unbinds the action.
+pub fn parse_sequences(s: &str) -> Result<Vec<KeySequence>, String> {
+    s.split(',')
+        .map(str::trim)
+        .filter(|alternative| !alternative.is_empty())
+        .map(|alternative| alternative.split_whitespace().map(str::parse).collect())
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Keymap {
+    bindings: Vec<(KeySequence, Action)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let bindings = Action::ALL
+            .into_iter()
+            .flat_map(|action| {
+                parse_sequences(action.default_keys())
+                    .expect("default keys parse")
+                    .into_iter()
+                    .map(move |sequence| (sequence, action))
+            })
+            .collect();
+        Self { bindings }
+    }
+}
+
+impl Keymap {
+    /// The defaults with the actions named in `keys` rebound. A default key
+    /// taken by an override is dropped from the action it belonged to.
+    pub fn from_config(keys: &BTreeMap<String, String>) -> Result<Self, String> {
+        let mut bindings = Vec::new();
+        let mut overridden = Vec::new();
+        for (name, value) in keys {
+            let action: Action = name.parse()?;
+            let sequences = parse_sequences(value).map_err(|e| format!("{}: {}", name, e))?;
+            overridden.push(action);
+            bindings.extend(sequences.into_iter().map(|sequence| (sequence, action)));
+        }
+
+        for (index, (sequence, action)) in bindings.iter().enumerate() {
+            if let Some((other, other_action)) = bindings[index + 1..]
+                .iter()
+                .find(|(other, _)| overlaps(sequence, other))
+            {
+                return Err(if sequence == other {
+                    format!(
+                        "{} is bound to both {} and {}",
+                        display(sequence),
+                        action.name(),
+                        other_action.name()
+                    )
+                } else {
+                    // A sequence that starts another could never wait for the rest
+                    format!(
+                        "{} ({}) and {} ({}) start the same way",
+                        display(sequence),
+                        action.name(),
+                        display(other),
+                        other_action.name()
+                    )
+                });
+            }
+        }
+
+        for (sequence, action) in Self::default().bindings {
+            let taken = bindings.iter().any(|(other, _)| overlaps(&sequence, other));
+            if !overridden.contains(&action) && !taken {
+                bindings.push((sequence, action));
+            }
+        }
+        if !bindings.iter().any(|(_, action)| *action == Action::Quit) {
+            return Err("quit has no key left; give it another one".to_string());
+        }
+        Ok(Self { bindings })
+    }
+
+    /// Adds `key` to the keys pressed so far and returns the action once they
+    /// complete a binding. Keys that can't lead anywhere are dropped.
+    pub fn resolve(&self, pending: &mut KeySequence, key: KeyChord) -> Option<Action> {
+        pending.push(key);
+        loop {
+            if let Some((_, action)) = self.bindings.iter().find(|(s, _)| s == pending) {
+                pending.clear();
+                return Some(*action);
+            }
+            if self.bindings.iter().any(|(s, _)| s.starts_with(pending)) {
+                return None;
+            }
+            if pending.len() == 1 {
+                pending.clear();
+                return None;
+            }
+            // The sequence broke off; the last key may start one of its own
+            pending.drain(..pending.len() - 1);
+        }
+    }
+
+    /// The keys bound to `action`, as shown to the user ("↑ k").
+    pub fn keys_for(&self, action: Action) -> String {
+        let keys: Vec<String> = self
+            .bindings
+            .iter()
+            .filter(|(_, a)| *a == action)
+            .map(|(sequence, _)| display(sequence))
+            .collect();
+        if keys.is_empty() {
+            "unbound".to_string()
+        } else {
+            keys.join(" ")
+        }
+    }
+
+    /// The first key bound to `action`, for short hints.
+    pub fn key_for(&self, action: Action) -> Option<String> {
+        self.bindings
+            .iter()
+            .find(|(_, a)| *a == action)
+            .map(|(sequence, _)| display(sequence))
+    }
+}
+
+fn overlaps(a: &[KeyChord], b: &[KeyChord]) -> bool {
+    a.starts_with(b) || b.starts_with(a)
+}
+
+pub fn display(sequence: &[KeyChord]) -> String {
+    let keys: Vec<String> = sequence.iter().map(ToString::to_string).collect();
+    keys.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(s: &str) -> KeyChord {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_overrides_and_resolves_sequences() {
+        assert_eq!(
+            key("ctrl+x"),
+            KeyChord::new(KeyCode::Char('x'), KeyModifiers::CONTROL)
+        );
+        assert_eq!(key("shift+j"), key("J"));
+        assert_eq!(
+            KeyChord::from_event(&KeyEvent::new(KeyCode::Char('J'), KeyModifiers::SHIFT)),
+            key("J")
+        );
+        assert_eq!(key("f5"), KeyChord::new(KeyCode::F(5), KeyModifiers::NONE));
+        assert!("hyper+x".parse::<KeyChord>().is_err());
+        assert!("ctrl+".parse::<KeyChord>().is_err());
+
+        let keymap = Keymap::default();
+        let mut pending = Vec::new();
+        assert_eq!(keymap.resolve(&mut pending, key("j")), Some(Action::Down));
+        assert_eq!(keymap.resolve(&mut pending, key("up")), Some(Action::Up));
+        assert_eq!(keymap.keys_for(Action::Up), "↑ k");
+
+        let config = BTreeMap::from([
+            ("quit".to_string(), "ctrl+q, g q".to_string()),
+            ("history".to_string(), "g h".to_string()),
+            ("help".to_string(), "q".to_string()),
+        ]);
+        let keymap = Keymap::from_config(&config).unwrap();
+        // `q` moved to help, `h` is free now that history is rebound
+        assert_eq!(keymap.resolve(&mut pending, key("q")), Some(Action::Help));
+        assert_eq!(keymap.resolve(&mut pending, key("h")), None);
+        assert_eq!(keymap.resolve(&mut pending, key("g")), None);
+        assert_eq!(
+            keymap.resolve(&mut pending, key("h")),
+            Some(Action::History)
+        );
+        assert_eq!(
+            keymap.resolve(&mut pending, key("ctrl+q")),
+            Some(Action::Quit)
+        );
+        // A broken sequence falls back to the last key on its own
+        assert_eq!(keymap.resolve(&mut pending, key("g")), None);
+        assert_eq!(keymap.resolve(&mut pending, key("j")), Some(Action::Down));
+        assert!(pending.is_empty());
+
+        let clash = |pairs: &[(&str, &str)]| {
+            let config = pairs
+                .iter()
+                .map(|(a, k)| (a.to_string(), k.to_string()))
+                .collect();
+            Keymap::from_config(&config)
+        };
+        assert!(clash(&[("quit", "x"), ("help", "x")]).is_err());
+        assert!(clash(&[("quit", "g"), ("help", "g g")]).is_err());
+        assert!(clash(&[("quit", "")]).is_err());
+        assert!(clash(&[("help", "q")]).is_err());
+        assert!(clash(&[("launch", "x")]).is_err());
+    }
+}
@@ -1,5 +1,26 @@
 pub mod app;
+pub mod batch;
+pub mod bench;
+pub mod cli;
 pub mod compression;
+pub mod config;
+pub mod estimate;
+pub mod frames;
+pub mod help;
+pub mod history;
+pub mod info;
+pub mod input;
+pub mod keymap;
+pub mod mouse;
+pub mod output;
+pub mod progress;
+pub mod report;
+pub mod seekable;
+pub mod settings;
+pub mod sniff;
+pub mod sparse;
+pub mod store;
+pub mod theme;
 pub mod ui;
 
 pub use app::*;
@@ -7,14 +28,22 @@ pub use compression::*;
 pub use ui::*;
 /// The three compression presets exposed to the user.
 /// Up/Down arrows cycle through them.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum CompressionLevel {
-    Fast,   // zstd level 1
+    Fast, // zstd level 1
+    #[default]
     Normal, // zstd level 3 (zstd default)
-    Best,   // zstd level 19
+    Best, // zstd level 19
 }
 
 impl CompressionLevel {
+    pub const ALL: [CompressionLevel; 3] = [
+        CompressionLevel::Fast,
+        CompressionLevel::Normal,
+        CompressionLevel::Best,
+    ];
+
     /// The zstd integer level to pass to the encoder.
     pub fn zstd_level(self) -> i32 {
         match self {
@@ -52,6 +81,262 @@ impl CompressionLevel {
     }
 }
 
+impl std::str::FromStr for CompressionLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "fast" => Ok(CompressionLevel::Fast),
+            "normal" => Ok(CompressionLevel::Normal),
+            "best" => Ok(CompressionLevel::Best),
+            _ => Err(format!(
+                "unknown level '{}' (expected fast, normal or best)",
+                s
+            )),
+        }
+    }
+}
+
+/// How the output frames are produced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Algorithm {
+    #[default]
+    Zstd,
+    /// Input copied verbatim into raw zstd blocks: no CPU spent on compression,
+    /// but the output is still a `.zst` any zstd tool can read.
+    Store,
+}
+
+impl Algorithm {
+    pub fn label(self) -> &'static str {
+        match self {
+            Algorithm::Zstd => "zstd",
+            Algorithm::Store => "store",
+        }
+    }
+}
+
+impl std::str::FromStr for Algorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "zstd" => Ok(Algorithm::Zstd),
+            "store" => Ok(Algorithm::Store),
+            _ => Err(format!(
+                "unknown algorithm '{}' (expected zstd or store)",
+                s
+            )),
+        }
+    }
+}
+
+/// What to do when the output file already exists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OverwritePolicy {
+    #[default]
+    Always,
+    /// Fail the job and leave the existing file untouched.
+    Never,
+}
+
+impl OverwritePolicy {
+    pub fn label(self) -> &'static str {
+        match self {
+            OverwritePolicy::Always => "always",
+            OverwritePolicy::Never => "never",
+        }
+    }
+}
+
+impl std::str::FromStr for OverwritePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "always" => Ok(OverwritePolicy::Always),
+            "never" => Ok(OverwritePolicy::Never),
+            _ => Err(format!(
+                "unknown overwrite policy '{}' (expected always or never)",
+                s
+            )),
+        }
+    }
+}
+
+/// How compression jobs read their input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReadMode {
+    /// Mapped from `MAPPED_MIN_SIZE` up, buffered below and for files that
+    /// users other than the owner can write to (on Unix).
+    #[default]
+    Auto,
+    /// Through a buffer of `JobOptions::buffer_size` bytes.
+    Buffered,
+    /// Mapped into memory, or with `Algorithm::Store` copied from file to file by
+    /// the kernel. Falls back to buffered reads for files that can't be mapped.
+    Mapped,
+}
+
+/// Below this, mapping a file costs about as much as the copies it saves.
+pub const MAPPED_MIN_SIZE: u64 = 64 * 1024 * 1024;
+
+impl ReadMode {
+    pub fn label(self) -> &'static str {
+        match self {
+            ReadMode::Auto => "auto",
+            ReadMode::Buffered => "buffered",
+            ReadMode::Mapped => "mapped",
+        }
+    }
+
+    /// Whether an input of `size` bytes skips the buffer, if it can be mapped.
+    pub fn maps(self, size: u64) -> bool {
+        match self {
+            ReadMode::Auto => size >= MAPPED_MIN_SIZE,
+            ReadMode::Buffered => false,
+            ReadMode::Mapped => true,
+        }
+    }
+}
+
+impl std::str::FromStr for ReadMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "auto" => Ok(ReadMode::Auto),
+            "buffered" => Ok(ReadMode::Buffered),
+            "mapped" => Ok(ReadMode::Mapped),
+            _ => Err(format!(
+                "unknown read mode '{}' (expected auto, buffered or mapped)",
+                s
+            )),
+        }
+    }
+}
+
+/// Parses a byte count with an optional binary suffix: 4096, 64K, 4M, 1G.
+pub fn parse_size(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let (digits, multiplier) = match s.char_indices().last() {
+        Some((i, 'k' | 'K')) => (&s[..i], 1 << 10),
+        Some((i, 'm' | 'M')) => (&s[..i], 1 << 20),
+        Some((i, 'g' | 'G')) => (&s[..i], 1 << 30),
+        _ => (s, 1),
+    };
+    digits
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(multiplier))
+        .ok_or_else(|| format!("invalid size '{}'", s))
+}
+
+/// Formats a byte count with binary units: 512 B, 1.50 KiB, 3.20 GiB.
+pub fn human_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.2} {}", value, UNITS[unit])
+    }
+}
+
+/// Per-job settings beyond the compression level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JobOptions {
+    pub algorithm: Algorithm,
+    /// Delete the input once the output is fully written, synced and (if enabled) verified.
+    pub remove_source: bool,
+    /// Decode the compressed output again and compare it with the input before finishing.
+    pub verify: bool,
+    /// Write the seekable format with frames of this many input bytes instead of one stream.
+    /// Ignored by `Algorithm::Store`.
+    pub seekable_frame_size: Option<u32>,
+    /// Worker threads for decompressing multi-frame files; 0 picks one per core.
+    pub threads: usize,
+    pub overwrite: OverwritePolicy,
+    /// Store a checksum of the content in each zstd frame. Ignored by `Algorithm::Store`.
+    pub checksum: bool,
+    /// Long-distance matching: finds repeats far apart in big inputs, at the cost
+    /// of a 128 MiB window. Ignored by `Algorithm::Store`.
+    pub long_distance: bool,
+    /// Bytes read and written at a time; 0 picks `DEFAULT_BUFFER_SIZE`.
+    pub buffer_size: usize,
+    pub progress: progress::ProgressMode,
+    pub read: ReadMode,
+    /// Leave holes for blocks of zeros when decompressing. Ignored by compression.
+    pub sparse: bool,
+}
+
+impl Default for JobOptions {
+    fn default() -> Self {
+        Self {
+            algorithm: Algorithm::default(),
+            remove_source: false,
+            verify: false,
+            seekable_frame_size: None,
+            threads: 0,
+            overwrite: OverwritePolicy::default(),
+            checksum: false,
+            long_distance: false,
+            buffer_size: 0,
+            progress: progress::ProgressMode::default(),
+            read: ReadMode::default(),
+            sparse: true,
+        }
+    }
+}
+
+/// Big enough that reads, writes and progress reports are a small part of a job.
+pub const DEFAULT_BUFFER_SIZE: usize = 1024 * 1024;
+/// Limits for `buffer_size`.
+pub const BUFFER_SIZES: std::ops::RangeInclusive<usize> = 4 * 1024..=256 * 1024 * 1024;
+
+/// Accepts 0, for the default, and sizes within `BUFFER_SIZES`.
+pub fn validate_buffer_size(size: usize) -> Result<(), String> {
+    if size != 0 && !BUFFER_SIZES.contains(&size) {
+        return Err(format!(
+            "buffer size must be between {} and {}",
+            human_bytes(*BUFFER_SIZES.start() as u64),
+            human_bytes(*BUFFER_SIZES.end() as u64)
+        ));
+    }
+    Ok(())
+}
+
+/// A `parse_size` within `BUFFER_SIZES`, or 0 for the default.
+pub fn parse_buffer_size(s: &str) -> Result<usize, String> {
+    let size = usize::try_from(parse_size(s)?).map_err(|_| format!("invalid size '{}'", s))?;
+    validate_buffer_size(size)?;
+    Ok(size)
+}
+
+impl JobOptions {
+    pub fn worker_threads(&self) -> usize {
+        match self.threads {
+            0 => std::thread::available_parallelism().map_or(1, |n| n.get()),
+            n => n,
+        }
+    }
+
+    pub fn buffer_size(&self) -> usize {
+        match self.buffer_size {
+            0 => DEFAULT_BUFFER_SIZE,
+            n => n,
+        }
+    }
+}
+
 pub enum CompressMessage {
     Progress {
         bytes_processed: u64,
@@ -61,6 +346,11 @@ pub enum CompressMessage {
         original_size: u64,
         compressed_size: u64,
         output_path: String,
+        source_removed: bool,
     },
     Error(String),
+    /// Sent once by a batch job instead of `Finished`.
+    BatchFinished(batch::BatchReport),
+    /// Sent first, instead of any `Progress`, by jobs run with `ProgressMode::Counter`.
+    Counter(progress::ProgressCounter),
 }
@@ -1,14 +1,42 @@
 use std::io;
+use std::process::ExitCode;
 
+use clap::Parser;
 use freya::App;
+use freya::cli::Cli;
+use freya::config::Config;
+use freya::report;
 
-fn main() -> io::Result<()> {
-    let mut app = App::default();
+fn main() -> io::Result<ExitCode> {
+    let cli = Cli::parse();
+    // Settings changed in the TUI are saved to the file without the overrides
+    let loaded = Config::load_file().and_then(|file| Ok((file.clone(), file.with_env()?)));
+    let (file_config, config) = match loaded {
+        Ok(configs) => configs,
+        Err(e) => {
+            eprintln!("Error: config: {}", e);
+            return Ok(ExitCode::FAILURE);
+        }
+    };
+    if let Some(command) = cli.command {
+        return Ok(freya::cli::run(command, &config));
+    }
+
+    let mut config = config;
+    if let Err(e) = cli.settings.apply(&mut config) {
+        eprintln!("Error: {}", e);
+        return Ok(ExitCode::FAILURE);
+    }
+    let mut app = App::new(config).with_file_config(file_config);
     ratatui::run(|terminal| app.run(terminal))?;
 
-    if let Some(result) = app.last_compression_result {
+    if cli.json {
+        if let Some(result) = &app.last_result {
+            println!("{}", report::to_line(&report::Event::Result(result)));
+        }
+    } else if let Some(result) = app.last_compression_result {
         println!("{}", result);
     }
 
-    Ok(())
+    Ok(ExitCode::SUCCESS)
 }
@@ -0,0 +1,92 @@
+//! Mouse support. While drawing, the UI records where each clickable thing
+//! landed; a click looks its position up here and the app handles the result
+//! the same way as the key it stands for.
+
+use crate::CompressionLevel;
+use crate::keymap::Action;
+use ratatui::layout::{Position, Rect};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    /// A key hint or button: does what its key does.
+    Action(Action),
+    /// One option of the level selector.
+    Level(CompressionLevel),
+    /// A row of the list in the open panel.
+    Row(usize),
+}
+
+/// Clickable areas of the last frame drawn.
+#[derive(Debug, Default)]
+pub struct Targets {
+    areas: Vec<(Rect, Target)>,
+}
+
+impl Targets {
+    pub fn clear(&mut self) {
+        self.areas.clear();
+    }
+
+    pub fn push(&mut self, area: Rect, target: Target) {
+        self.areas.push((area, target));
+    }
+
+    /// Lays `pieces` (width, target) out left to right from `x` on row `y`,
+    /// stopping at `right`, the first column past the space they are drawn in.
+    pub fn push_row(&mut self, x: u16, y: u16, right: u16, pieces: &[(u16, Option<Target>)]) {
+        let mut x = x;
+        for &(width, target) in pieces {
+            let width = width.min(right.saturating_sub(x));
+            if let Some(target) = target
+                && width > 0
+            {
+                self.push(Rect::new(x, y, width, 1), target);
+            }
+            x += width;
+        }
+    }
+
+    /// What is under the given cell; things drawn later sit on top.
+    pub fn at(&self, column: u16, row: u16) -> Option<Target> {
+        self.areas
+            .iter()
+            .rev()
+            .find(|(area, _)| area.contains(Position::new(column, row)))
+            .map(|&(_, target)| target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_the_topmost_target_and_clips_rows() {
+        let mut targets = Targets::default();
+        targets.push(Rect::new(0, 0, 20, 10), Target::Row(0));
+        targets.push_row(
+            2,
+            3,
+            10,
+            &[
+                (3, Some(Target::Action(Action::Quit))),
+                (2, None),
+                (10, Some(Target::Level(CompressionLevel::Best))),
+            ],
+        );
+        assert_eq!(targets.at(2, 3), Some(Target::Action(Action::Quit)));
+        assert_eq!(targets.at(4, 3), Some(Target::Action(Action::Quit)));
+        // The gap between pieces falls through to whatever is underneath
+        assert_eq!(targets.at(5, 3), Some(Target::Row(0)));
+        assert_eq!(
+            targets.at(9, 3),
+            Some(Target::Level(CompressionLevel::Best))
+        );
+        // Clipped at `right`
+        assert_eq!(targets.at(10, 3), Some(Target::Row(0)));
+        assert_eq!(targets.at(30, 3), None);
+
+        targets.clear();
+        assert_eq!(targets.at(2, 3), None);
+    }
+}
@@ -0,0 +1,243 @@
+//! Where job outputs go: next to the input or in an output directory, named
+//! by a template such as `{stem}-{date}.{ext}.zst`.
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// "document.pdf" -> "document.pdf.zst"
+pub const DEFAULT_TEMPLATE: &str = "{name}.zst";
+/// Placeholders a template may use.
+pub const PLACEHOLDERS: [&str; 4] = ["{name}", "{stem}", "{ext}", "{date}"];
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct OutputNaming {
+    /// Write here instead of next to the input; created when missing.
+    pub dir: Option<PathBuf>,
+    /// File name template for compressed outputs, `DEFAULT_TEMPLATE` when unset.
+    pub template: Option<String>,
+}
+
+impl OutputNaming {
+    /// Output path for compressing `input`.
+    pub fn compressed(&self, input: &Path) -> Result<PathBuf, String> {
+        let template = self.template.as_deref().unwrap_or(DEFAULT_TEMPLATE);
+        let name = render_template(template, input, &today())?;
+        Ok(self.parent_for(input).join(name))
+    }
+
+    /// Output path for decompressing `input`: the name minus `.zst`, or plus
+    /// `.out` for names without it. Templates only apply to compression, since
+    /// the original name can't be recovered from one.
+    pub fn decompressed(&self, input: &Path) -> PathBuf {
+        let name = match input.extension() {
+            Some(ext) if ext.eq_ignore_ascii_case("zst") => input.with_extension(""),
+            _ => {
+                let mut name = input.as_os_str().to_os_string();
+                name.push(".out");
+                PathBuf::from(name)
+            }
+        };
+        match (&self.dir, name.file_name()) {
+            (Some(dir), Some(file_name)) => dir.join(file_name),
+            _ => name,
+        }
+    }
+
+    /// The same naming with `subdir` appended to the output dir, so batch jobs
+    /// can mirror the folder structure. Without an output dir nothing changes.
+    pub fn nested(&self, subdir: &Path) -> Self {
+        Self {
+            dir: self.dir.as_ref().map(|dir| dir.join(subdir)),
+            template: self.template.clone(),
+        }
+    }
+
+    fn parent_for(&self, input: &Path) -> PathBuf {
+        match &self.dir {
+            Some(dir) => dir.clone(),
+            None => input.parent().map(Path::to_path_buf).unwrap_or_default(),
+        }
+    }
+}
+
+/// Fills in the placeholders for `input`. With no extension `.{ext}` disappears
+/// entirely, so `{stem}.{ext}.zst` gives `Makefile.zst` rather than `Makefile..zst`.
+pub fn render_template(template: &str, input: &Path, date: &str) -> Result<String, String> {
+    validate_template(template)?;
+    let name = input.file_name().unwrap_or_default().to_string_lossy();
+    let stem = input.file_stem().unwrap_or_default().to_string_lossy();
+    let ext = input.extension().unwrap_or_default().to_string_lossy();
+
+    let mut rendered = template.to_string();
+    if ext.is_empty() {
+        rendered = rendered.replace(".{ext}", "");
+    }
+    let rendered = rendered
+        .replace("{name}", &name)
+        .replace("{stem}", &stem)
+        .replace("{ext}", &ext)
+        .replace("{date}", date);
+    if rendered.is_empty() || rendered == "." || rendered == ".." {
+        return Err(format!("template '{}' gives an empty file name", template));
+    }
+    Ok(rendered)
+}
+
+/// Rejects unknown placeholders, path separators, and templates that give
+/// back the input's own name, like `{name}` or `{stem}.{ext}`.
+pub fn validate_template(template: &str) -> Result<(), String> {
+    if template.trim().is_empty() {
+        return Err("template is empty".to_string());
+    }
+    let literal = ["{name}", "{stem}", "{ext}"]
+        .iter()
+        .fold(template.to_string(), |rest, placeholder| {
+            rest.replace(placeholder, "")
+        });
+    if literal.chars().all(|c| c == '.') {
+        return Err(format!(
+            "template '{}' adds nothing to the input's name, so the output would replace the input",
+            template
+        ));
+    }
+    if template.contains(['/', '\\']) {
+        return Err("template must be a file name, not a path (use the output directory)".into());
+    }
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| format!("unclosed '{{' in template '{}'", template))?;
+        let placeholder = &rest[start..start + end + 1];
+        if !PLACEHOLDERS.contains(&placeholder) {
+            return Err(format!(
+                "unknown placeholder {} (expected one of {})",
+                placeholder,
+                PLACEHOLDERS.join(", ")
+            ));
+        }
+        rest = &rest[start + end + 1..];
+    }
+    Ok(())
+}
+
+/// Whether `a` and `b` are the same existing file, through links or different
+/// spellings of the path too.
+pub fn same_file(a: &Path, b: &Path) -> io::Result<bool> {
+    let (a, b) = match (std::fs::metadata(a), std::fs::metadata(b)) {
+        (Ok(a), Ok(b)) => (a, b),
+        (Err(e), _) | (_, Err(e)) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+        (Err(e), _) | (_, Err(e)) => return Err(e),
+    };
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        Ok((a.dev(), a.ino()) == (b.dev(), b.ino()))
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = (a, b);
+        Ok(std::fs::canonicalize(a)? == std::fs::canonicalize(b)?)
+    }
+}
+
+/// Creates the directory `path` will be written to, if it doesn't exist yet.
+pub fn create_parent(path: &Path) -> io::Result<()> {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => std::fs::create_dir_all(parent),
+        _ => Ok(()),
+    }
+}
+
+/// Today's UTC date as YYYY-MM-DD.
+pub fn today() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    date_from_unix(secs)
+}
+
+/// The UTC date of a Unix timestamp as YYYY-MM-DD.
+pub fn date_from_unix(secs: u64) -> String {
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+// Howard Hinnant's days-to-civil conversion for the proleptic Gregorian calendar.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_templates_and_places_outputs() {
+        let input = Path::new("/data/report.pdf");
+        let date = "2024-02-29";
+        assert_eq!(
+            render_template("{stem}-{date}.{ext}.zst", input, date).unwrap(),
+            "report-2024-02-29.pdf.zst"
+        );
+        assert_eq!(
+            render_template("{stem}.{ext}.zst", Path::new("Makefile"), date).unwrap(),
+            "Makefile.zst"
+        );
+        assert!(render_template("{stem}/{ext}", input, date).is_err());
+        assert!(render_template("{size}.zst", input, date).is_err());
+        assert!(render_template("{stem.zst", input, date).is_err());
+        // The input's own name would overwrite the input
+        assert!(validate_template("{name}").is_err());
+        assert!(validate_template("{stem}.{ext}").is_err());
+        assert!(validate_template("{name}-{date}").is_ok());
+
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(19_782), (2024, 2, 29));
+
+        let naming = OutputNaming::default();
+        assert_eq!(
+            naming.compressed(input).unwrap(),
+            PathBuf::from("/data/report.pdf.zst")
+        );
+        assert_eq!(
+            naming.decompressed(Path::new("/data/report.pdf.zst")),
+            PathBuf::from("/data/report.pdf")
+        );
+        assert_eq!(
+            naming.decompressed(Path::new("/data/blob")),
+            PathBuf::from("/data/blob.out")
+        );
+
+        let dir = std::env::temp_dir().join("freya_test_output");
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("blob");
+        std::fs::write(&file, b"data").unwrap();
+        assert!(same_file(&file, &dir.join(".").join("blob")).unwrap());
+        assert!(!same_file(&file, &dir.join("blob.out")).unwrap());
+        std::fs::remove_dir_all(&dir).ok();
+
+        let naming = OutputNaming {
+            dir: Some(PathBuf::from("/out")),
+            template: Some("{stem}.zst".to_string()),
+        };
+        assert_eq!(
+            naming.nested(Path::new("sub")).compressed(input).unwrap(),
+            PathBuf::from("/out/sub/report.zst")
+        );
+        assert_eq!(
+            naming.decompressed(Path::new("/data/report.pdf.zst")),
+            PathBuf::from("/out/report.pdf")
+        );
+    }
+}
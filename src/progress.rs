@@ -0,0 +1,224 @@
+//! How jobs tell whoever started them how far along they are. By default a
+//! job sends `CompressMessage::Progress`, at most every `INTERVAL` or every
+//! percent of the job; with `ProgressMode::Counter` it sends one
+//! `CompressMessage::Counter` up front and then only updates that counter,
+//! which the watcher reads whenever it likes.
+
+use crate::CompressMessage;
+use std::io;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+/// The TUI polls every 50 ms; more frequent reports would never be seen.
+pub const INTERVAL: Duration = Duration::from_millis(50);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ProgressMode {
+    /// Throttled `CompressMessage::Progress` messages.
+    #[default]
+    Messages,
+    /// A shared `ProgressCounter`; no messages until the job ends.
+    Counter,
+}
+
+/// Bytes processed and the total, shared between a job and its watcher. The job
+/// stops once it is the only one left holding the counter.
+#[derive(Debug, Clone, Default)]
+pub struct ProgressCounter(Arc<[AtomicU64; 2]>);
+
+impl ProgressCounter {
+    fn new(total_bytes: u64) -> Self {
+        Self(Arc::new([AtomicU64::new(0), AtomicU64::new(total_bytes)]))
+    }
+
+    pub fn bytes_processed(&self) -> u64 {
+        self.0[0].load(Ordering::Relaxed)
+    }
+
+    pub fn total_bytes(&self) -> u64 {
+        self.0[1].load(Ordering::Relaxed)
+    }
+
+    /// From 0.0 to 1.0; 0.0 until the total is known.
+    pub fn fraction(&self) -> f64 {
+        match self.total_bytes() {
+            0 => 0.0,
+            total => self.bytes_processed() as f64 / total as f64,
+        }
+    }
+
+    fn abandoned(&self) -> bool {
+        Arc::strong_count(&self.0) == 1
+    }
+}
+
+/// Reports a job's progress the way its `ProgressMode` asks.
+#[derive(Debug)]
+pub struct Reporter<'a> {
+    tx: &'a mpsc::Sender<CompressMessage>,
+    total_bytes: u64,
+    counter: Option<ProgressCounter>,
+    /// When, and at how many bytes, the last message went out.
+    last_sent: Option<(Instant, u64)>,
+}
+
+impl<'a> Reporter<'a> {
+    /// In counter mode this already hands the counter over, so it fails like
+    /// `report` if nobody listens.
+    pub fn new(
+        tx: &'a mpsc::Sender<CompressMessage>,
+        total_bytes: u64,
+        mode: ProgressMode,
+    ) -> io::Result<Self> {
+        let counter = match mode {
+            ProgressMode::Messages => None,
+            ProgressMode::Counter => {
+                let counter = ProgressCounter::new(total_bytes);
+                tx.send(CompressMessage::Counter(counter.clone()))
+                    .map_err(|_| stopped())?;
+                Some(counter)
+            }
+        };
+        Ok(Self {
+            tx,
+            total_bytes,
+            counter,
+            last_sent: None,
+        })
+    }
+
+    /// Records that `bytes_processed` of the total are done. Fails with
+    /// `stopped()` once nobody watches the job any more; in message mode that
+    /// is only noticed when a message is due, so at most `INTERVAL` late.
+    pub fn report(&mut self, bytes_processed: u64) -> io::Result<()> {
+        if let Some(counter) = &self.counter {
+            counter.0[0].store(bytes_processed, Ordering::Relaxed);
+            return match counter.abandoned() {
+                true => Err(stopped()),
+                false => Ok(()),
+            };
+        }
+        let step = (self.total_bytes / 100).max(1);
+        let due = bytes_processed >= self.total_bytes
+            || self.last_sent.is_none_or(|(at, bytes)| {
+                at.elapsed() >= INTERVAL || bytes_processed.saturating_sub(bytes) >= step
+            });
+        if due {
+            self.tx
+                .send(CompressMessage::Progress {
+                    bytes_processed,
+                    total_bytes: self.total_bytes,
+                })
+                .map_err(|_| stopped())?;
+            self.last_sent = Some((Instant::now(), bytes_processed));
+        }
+        Ok(())
+    }
+
+    /// For phases that make no progress of their own, like verifying: fails
+    /// like `report` once nobody watches. In message mode that means repeating
+    /// the last report, at most every `INTERVAL`.
+    pub fn check(&mut self) -> io::Result<()> {
+        if let Some(counter) = &self.counter {
+            return match counter.abandoned() {
+                true => Err(stopped()),
+                false => Ok(()),
+            };
+        }
+        match self.last_sent {
+            Some((at, bytes)) if at.elapsed() >= INTERVAL => {
+                self.last_sent = None;
+                self.report(bytes)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Once the work is done, before a step that can't be undone like removing
+    /// the source: reports the total and fails like `report` unless someone
+    /// still watches, however recently the last report went out.
+    pub fn confirm(&mut self) -> io::Result<()> {
+        if self.counter.is_some() {
+            return self.check();
+        }
+        self.last_sent = None;
+        self.report(self.total_bytes)
+    }
+
+    /// For jobs that only learn their total once under way.
+    pub fn set_total(&mut self, total_bytes: u64) {
+        self.total_bytes = total_bytes;
+        if let Some(counter) = &self.counter {
+            counter.0[1].store(total_bytes, Ordering::Relaxed);
+        }
+    }
+}
+
+/// The app stops a job by dropping its receiver (and counter): the next report
+/// fails and the job unwinds with this error.
+pub fn stopped() -> io::Error {
+    io::Error::new(io::ErrorKind::Interrupted, "stopped")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn throttles_messages_and_stops_when_abandoned() {
+        let (tx, rx) = mpsc::channel();
+        let total = 1 << 30;
+        let mut reporter = Reporter::new(&tx, total, ProgressMode::Messages).unwrap();
+        // 64 KiB steps, far faster than INTERVAL: one message per percent at most
+        for done in (0..=total).step_by(64 << 10) {
+            reporter.report(done).unwrap();
+        }
+        let sent: Vec<u64> = rx
+            .try_iter()
+            .map(|msg| match msg {
+                CompressMessage::Progress {
+                    bytes_processed,
+                    total_bytes,
+                } => {
+                    assert_eq!(total_bytes, total);
+                    bytes_processed
+                }
+                _ => panic!("unexpected message"),
+            })
+            .collect();
+        assert!(sent.len() <= 102, "{} messages", sent.len());
+        assert!(sent.is_sorted());
+        assert_eq!(sent.last(), Some(&total));
+        // Nothing new to report, so `check` repeats the last report to find out
+        std::thread::sleep(INTERVAL);
+        reporter.check().unwrap();
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(CompressMessage::Progress { .. })
+        ));
+        // Right after a report, only `confirm` still asks
+        drop(rx);
+        assert!(reporter.check().is_ok());
+        assert!(reporter.confirm().is_err());
+        assert!(reporter.report(total).is_err());
+
+        let (tx, rx) = mpsc::channel();
+        let mut reporter = Reporter::new(&tx, 1000, ProgressMode::Counter).unwrap();
+        let Ok(CompressMessage::Counter(counter)) = rx.try_recv() else {
+            panic!("no counter");
+        };
+        reporter.report(250).unwrap();
+        assert_eq!(counter.bytes_processed(), 250);
+        assert_eq!(counter.fraction(), 0.25);
+        assert!(rx.try_recv().is_err(), "counter mode sent a message");
+        reporter.set_total(500);
+        assert_eq!(counter.fraction(), 0.5);
+        drop(counter);
+        assert!(reporter.check().is_err());
+        assert!(reporter.confirm().is_err());
+        let e = reporter.report(500).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::Interrupted);
+    }
+}
@@ -0,0 +1,248 @@
+//! Machine-readable job results, printed by `--json`.
+//!
+//! Every line on stdout is one JSON object carrying the `schema` version and an
+//! `event`: `progress` (only with `--progress`) while the job runs, then exactly
+//! one `result`. Within a schema version fields are only ever added; renaming or
+//! removing one bumps `SCHEMA_VERSION`.
+
+use crate::batch::{BatchEntry, BatchReport};
+use crate::history::{HistoryEntry, JobStart};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Ok,
+    Failed,
+}
+
+/// The outcome of one job. The flattened fields are the same ones the history
+/// stores, so both read alike.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JobResult {
+    pub status: Status,
+    #[serde(flatten)]
+    pub entry: HistoryEntry,
+    pub source_removed: bool,
+    /// Every file of a folder job; absent for single files.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<FileResult>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileStatus {
+    Compressed,
+    Stored,
+    Skipped,
+    Failed,
+}
+
+/// One file of a folder job.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileResult {
+    pub status: FileStatus,
+    pub input: PathBuf,
+    pub output: Option<PathBuf>,
+    pub input_size: u64,
+    pub output_size: u64,
+    pub duration_ms: u64,
+    pub source_removed: bool,
+    /// Why the file was skipped or failed.
+    pub reason: Option<String>,
+}
+
+impl JobResult {
+    /// A job that ended with `CompressMessage::Finished`; the sizes are passed
+    /// through in the same order the message carries them.
+    pub fn finished(
+        job: &JobStart,
+        input_size: u64,
+        output_size: u64,
+        output_path: PathBuf,
+        source_removed: bool,
+    ) -> Self {
+        let mut entry = job.finished(input_size, output_size);
+        entry.output = Some(output_path);
+        Self {
+            status: Status::Ok,
+            entry,
+            source_removed,
+            files: Vec::new(),
+        }
+    }
+
+    pub fn failed(job: &JobStart, error: &str) -> Self {
+        Self {
+            status: Status::Failed,
+            entry: job.failed(error),
+            source_removed: false,
+            files: Vec::new(),
+        }
+    }
+
+    /// A folder job: totals over the files written, failed if any file failed.
+    pub fn batch(job: &JobStart, report: &BatchReport) -> Self {
+        let written = || report.compressed.iter().chain(&report.stored);
+        let input_size = written().map(|e| e.original_size).sum();
+        let output_size = written().map(|e| e.compressed_size).sum();
+        let mut entry = job.finished(input_size, output_size);
+        let status = if report.failed.is_empty() {
+            Status::Ok
+        } else {
+            let error = format!(
+                "{} of {} files failed",
+                report.failed.len(),
+                report.compressed.len()
+                    + report.stored.len()
+                    + report.skipped.len()
+                    + report.failed.len()
+            );
+            entry.error = Some(error);
+            Status::Failed
+        };
+
+        let file = |status, e: &BatchEntry| FileResult {
+            status,
+            input: e.path.clone(),
+            output: Some(e.output_path.clone()),
+            input_size: e.original_size,
+            output_size: e.compressed_size,
+            duration_ms: e.duration.as_millis() as u64,
+            source_removed: e.source_removed,
+            reason: None,
+        };
+        let untouched = |status, (path, reason): &(PathBuf, String)| FileResult {
+            status,
+            input: path.clone(),
+            output: None,
+            input_size: 0,
+            output_size: 0,
+            duration_ms: 0,
+            source_removed: false,
+            reason: Some(reason.clone()),
+        };
+        let files = (report
+            .compressed
+            .iter()
+            .map(|e| file(FileStatus::Compressed, e)))
+        .chain(report.stored.iter().map(|e| file(FileStatus::Stored, e)))
+        .chain(
+            report
+                .skipped
+                .iter()
+                .map(|s| untouched(FileStatus::Skipped, s)),
+        )
+        .chain(
+            report
+                .failed
+                .iter()
+                .map(|f| untouched(FileStatus::Failed, f)),
+        )
+        .collect();
+
+        Self {
+            status,
+            source_removed: written().any(|e| e.source_removed),
+            entry,
+            files,
+        }
+    }
+}
+
+/// What a line of `--json` output describes.
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "lowercase")]
+pub enum Event<'a> {
+    Progress {
+        bytes_processed: u64,
+        total_bytes: u64,
+    },
+    Result(&'a JobResult),
+}
+
+#[derive(Serialize)]
+struct Versioned<'a> {
+    schema: u32,
+    #[serde(flatten)]
+    event: &'a Event<'a>,
+}
+
+/// One line of `--json` output, without the trailing newline.
+pub fn to_line(event: &Event) -> String {
+    serde_json::to_string(&Versioned {
+        schema: SCHEMA_VERSION,
+        event,
+    })
+    .expect("events always serialize")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CompressionLevel;
+    use crate::history::JobKind;
+    use std::time::Duration;
+
+    #[test]
+    fn results_serialize_with_schema_and_event() {
+        let job = JobStart::new(
+            JobKind::Compress,
+            PathBuf::from("/data/a.log"),
+            None,
+            Some(CompressionLevel::Fast),
+        );
+        let result = JobResult::finished(&job, 200, 50, PathBuf::from("/out/a.log.zst"), true);
+        let line = to_line(&Event::Result(&result));
+        let value: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(value["schema"], SCHEMA_VERSION);
+        assert_eq!(value["event"], "result");
+        assert_eq!(value["status"], "ok");
+        assert_eq!(value["kind"], "compress");
+        assert_eq!(value["output"], "/out/a.log.zst");
+        assert_eq!(value["ratio"], 25.0);
+        assert_eq!(value["level"], "fast");
+        assert!(value.get("files").is_none());
+        // Readers on the same schema can parse the line back
+        assert_eq!(serde_json::from_str::<JobResult>(&line).unwrap(), result);
+
+        let progress = to_line(&Event::Progress {
+            bytes_processed: 5,
+            total_bytes: 10,
+        });
+        assert_eq!(
+            progress,
+            r#"{"schema":1,"event":"progress","bytes_processed":5,"total_bytes":10}"#
+        );
+
+        let report = BatchReport {
+            compressed: vec![BatchEntry {
+                path: PathBuf::from("/d/x.txt"),
+                output_path: PathBuf::from("/d/x.txt.zst"),
+                original_size: 100,
+                compressed_size: 10,
+                source_removed: false,
+                duration: Duration::from_millis(3),
+            }],
+            stored: Vec::new(),
+            skipped: vec![(PathBuf::from("/d/y.zst"), "already a .zst".to_string())],
+            failed: vec![(PathBuf::from("/d/z"), "permission denied".to_string())],
+        };
+        let result = JobResult::batch(&job, &report);
+        assert_eq!(result.status, Status::Failed);
+        assert_eq!(result.entry.error.as_deref(), Some("1 of 3 files failed"));
+        assert_eq!(result.entry.output_size, 10);
+        let statuses: Vec<FileStatus> = result.files.iter().map(|f| f.status).collect();
+        assert_eq!(
+            statuses,
+            [
+                FileStatus::Compressed,
+                FileStatus::Skipped,
+                FileStatus::Failed
+            ]
+        );
+    }
+}
@@ -0,0 +1,450 @@
+//! The zstd seekable format: the input is cut into independent frames of a fixed
+//! decompressed size, followed by a skippable frame holding a seek table. Any zstd
+//! decoder reads it as a normal multi-frame file, while `SeekableReader` can jump
+//! straight to the frame containing a given offset.
+//!
+//! Layout of the seek table (all integers little-endian):
+//!
+//! ```text
+//! Skippable_Magic_Number (u32) | Frame_Size (u32)
+//! Compressed_Size (u32) | Decompressed_Size (u32) [| Checksum (u32)]   x Number_Of_Frames
+//! Number_Of_Frames (u32) | Seek_Table_Descriptor (u8) | Seekable_Magic_Number (u32)
+//! ```
+
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use zstd::stream::{Decoder, Encoder};
+
+pub const SKIPPABLE_MAGIC: u32 = 0x184D_2A5E;
+pub const SEEKABLE_MAGIC: u32 = 0x8F92_EAB1;
+pub const DEFAULT_FRAME_SIZE: u32 = 1024 * 1024;
+/// Keeps every compressed frame comfortably inside the u32 size field.
+pub const MAX_FRAME_SIZE: u32 = 1024 * 1024 * 1024;
+
+const FOOTER_LEN: u64 = 9;
+const SKIPPABLE_HEADER_LEN: u64 = 8;
+const CHECKSUM_FLAG: u8 = 1 << 7;
+const RESERVED_BITS: u8 = 0b0111_1100;
+
+/// One frame in the seek table, with offsets resolved from the sizes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SeekEntry {
+    pub compressed_offset: u64,
+    pub decompressed_offset: u64,
+    pub compressed_size: u32,
+    pub decompressed_size: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SeekTable {
+    pub entries: Vec<SeekEntry>,
+    /// Whether the table carried per-frame checksums. They aren't kept, let
+    /// alone checked, so `write_to` always leaves them out.
+    pub has_checksums: bool,
+}
+
+impl SeekTable {
+    fn push(&mut self, compressed_size: u32, decompressed_size: u32) {
+        let (compressed_offset, decompressed_offset) = self
+            .entries
+            .last()
+            .map(|e| {
+                (
+                    e.compressed_offset + e.compressed_size as u64,
+                    e.decompressed_offset + e.decompressed_size as u64,
+                )
+            })
+            .unwrap_or((0, 0));
+        self.entries.push(SeekEntry {
+            compressed_offset,
+            decompressed_offset,
+            compressed_size,
+            decompressed_size,
+        });
+    }
+
+    pub fn compressed_len(&self) -> u64 {
+        self.entries
+            .last()
+            .map_or(0, |e| e.compressed_offset + e.compressed_size as u64)
+    }
+
+    pub fn decompressed_len(&self) -> u64 {
+        self.entries
+            .last()
+            .map_or(0, |e| e.decompressed_offset + e.decompressed_size as u64)
+    }
+
+    /// Index of the frame holding decompressed byte `offset`, if it's in range.
+    pub fn frame_at(&self, offset: u64) -> Option<usize> {
+        if offset >= self.decompressed_len() {
+            return None;
+        }
+        Some(
+            self.entries
+                .partition_point(|e| e.decompressed_offset + e.decompressed_size as u64 <= offset),
+        )
+    }
+
+    /// Size of the skippable frame the table took up in the file it was read from.
+    pub fn encoded_len(&self) -> u64 {
+        skippable_len(self.entries.len(), self.has_checksums)
+    }
+
+    /// Writes the table without checksums, whatever `has_checksums` says:
+    /// made-up ones would fail any reader that checks them.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let content_len = skippable_len(self.entries.len(), false) - SKIPPABLE_HEADER_LEN;
+        let content_len = u32::try_from(content_len)
+            .map_err(|_| invalid_data("seek table too large for a skippable frame"))?;
+        writer.write_all(&SKIPPABLE_MAGIC.to_le_bytes())?;
+        writer.write_all(&content_len.to_le_bytes())?;
+        for entry in &self.entries {
+            writer.write_all(&entry.compressed_size.to_le_bytes())?;
+            writer.write_all(&entry.decompressed_size.to_le_bytes())?;
+        }
+        writer.write_all(&(self.entries.len() as u32).to_le_bytes())?;
+        writer.write_all(&[0])?;
+        writer.write_all(&SEEKABLE_MAGIC.to_le_bytes())
+    }
+
+    /// Reads the seek table from the end of a seekable file. Errors if the file
+    /// isn't in the seekable format or the table doesn't add up to the file size.
+    pub fn read_from<R: Read + Seek>(reader: &mut R) -> io::Result<Self> {
+        let file_len = reader.seek(SeekFrom::End(0))?;
+        if file_len < SKIPPABLE_HEADER_LEN + FOOTER_LEN {
+            return Err(invalid_data("file too small to hold a seek table"));
+        }
+
+        let mut footer = [0u8; FOOTER_LEN as usize];
+        reader.seek(SeekFrom::End(-(FOOTER_LEN as i64)))?;
+        reader.read_exact(&mut footer)?;
+        if read_u32(&footer[5..9]) != SEEKABLE_MAGIC {
+            return Err(invalid_data("not a seekable zstd file (no seek table)"));
+        }
+        let frame_count = read_u32(&footer[0..4]) as u64;
+        let descriptor = footer[4];
+        if descriptor & RESERVED_BITS != 0 {
+            return Err(invalid_data("seek table descriptor has reserved bits set"));
+        }
+
+        let mut table = SeekTable {
+            entries: Vec::new(),
+            has_checksums: descriptor & CHECKSUM_FLAG != 0,
+        };
+        let entry_len = if table.has_checksums { 12 } else { 8 };
+        let table_len = SKIPPABLE_HEADER_LEN + frame_count * entry_len + FOOTER_LEN;
+        if table_len > file_len {
+            return Err(invalid_data("seek table larger than the file"));
+        }
+
+        let mut raw = vec![0u8; table_len as usize];
+        reader.seek(SeekFrom::Start(file_len - table_len))?;
+        reader.read_exact(&mut raw)?;
+        if read_u32(&raw[0..4]) != SKIPPABLE_MAGIC
+            || read_u32(&raw[4..8]) as u64 != table_len - SKIPPABLE_HEADER_LEN
+        {
+            return Err(invalid_data(
+                "seek table is not wrapped in a skippable frame",
+            ));
+        }
+
+        for entry in raw[8..raw.len() - FOOTER_LEN as usize].chunks_exact(entry_len as usize) {
+            table.push(read_u32(&entry[0..4]), read_u32(&entry[4..8]));
+        }
+        if table.compressed_len() != file_len - table_len {
+            return Err(invalid_data("seek table does not match the file size"));
+        }
+        Ok(table)
+    }
+}
+
+/// Writes the seekable format: every `frame_size` input bytes become their own
+/// zstd frame, and `finish` appends the seek table.
+pub struct SeekableWriter<W: Write> {
+    // Exactly one of these is set: the writer is moved into the encoder while a
+    // frame is open and handed back when the frame is finished.
+    writer: Option<CountingWriter<W>>,
+    encoder: Option<Encoder<'static, CountingWriter<W>>>,
+    level: i32,
+    checksum: bool,
+    long_distance: bool,
+    frame_size: u32,
+    frame_written: u32,
+    frame_start: u64,
+    table: SeekTable,
+}
+
+impl<W: Write> SeekableWriter<W> {
+    pub fn new(writer: W, level: i32, frame_size: u32) -> io::Result<Self> {
+        if frame_size == 0 || frame_size > MAX_FRAME_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("frame size must be between 1 and {} bytes", MAX_FRAME_SIZE),
+            ));
+        }
+        Ok(Self {
+            writer: Some(CountingWriter {
+                inner: writer,
+                count: 0,
+            }),
+            encoder: None,
+            level,
+            checksum: false,
+            long_distance: false,
+            frame_size,
+            frame_written: 0,
+            frame_start: 0,
+            table: SeekTable::default(),
+        })
+    }
+
+    /// Adds a content checksum to every frame, like `Encoder::include_checksum`.
+    pub fn include_checksum(&mut self, include: bool) {
+        self.checksum = include;
+    }
+
+    /// Enables long-distance matching within each frame, like `Encoder::long_distance_matching`.
+    pub fn long_distance_matching(&mut self, enable: bool) {
+        self.long_distance = enable;
+    }
+
+    fn end_frame(&mut self) -> io::Result<()> {
+        if let Some(encoder) = self.encoder.take() {
+            let writer = encoder.finish()?;
+            let compressed_size = u32::try_from(writer.count - self.frame_start)
+                .map_err(|_| invalid_data("compressed frame exceeds 4 GiB"))?;
+            self.table.push(compressed_size, self.frame_written);
+            self.frame_start = writer.count;
+            self.frame_written = 0;
+            self.writer = Some(writer);
+        }
+        Ok(())
+    }
+
+    /// Closes the last frame, appends the seek table and returns the inner writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.end_frame()?;
+        let mut writer = self.writer.take().expect("no frame open after end_frame");
+        self.table.write_to(&mut writer)?;
+        Ok(writer.inner)
+    }
+}
+
+impl<W: Write> Write for SeekableWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if self.encoder.is_none() {
+            let writer = self
+                .writer
+                .take()
+                .expect("writer missing while no frame open");
+            let mut encoder = Encoder::new(writer, self.level)?;
+            encoder.include_checksum(self.checksum)?;
+            encoder.long_distance_matching(self.long_distance)?;
+            self.encoder = Some(encoder);
+        }
+
+        let room = (self.frame_size - self.frame_written) as usize;
+        let len = buf.len().min(room);
+        let encoder = self.encoder.as_mut().expect("frame just opened");
+        let written = encoder.write(&buf[..len])?;
+        self.frame_written += written as u32;
+
+        if self.frame_written == self.frame_size {
+            self.end_frame()?;
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match (&mut self.encoder, &mut self.writer) {
+            (Some(encoder), _) => encoder.flush(),
+            (None, Some(writer)) => writer.flush(),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+/// `Read + Seek` over the decompressed contents of a seekable file. Only the
+/// frame containing the current position is decoded.
+pub struct SeekableReader<R> {
+    inner: R,
+    table: SeekTable,
+    position: u64,
+    // Reads are usually sequential, so keep the last decoded frame around
+    // instead of decompressing it again for every small read.
+    cached: Option<(usize, Vec<u8>)>,
+}
+
+impl<R: Read + Seek> SeekableReader<R> {
+    pub fn new(mut inner: R) -> io::Result<Self> {
+        let table = SeekTable::read_from(&mut inner)?;
+        Ok(Self {
+            inner,
+            table,
+            position: 0,
+            cached: None,
+        })
+    }
+
+    pub fn seek_table(&self) -> &SeekTable {
+        &self.table
+    }
+
+    /// Total decompressed length.
+    pub fn len(&self) -> u64 {
+        self.table.decompressed_len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    fn load_frame(&mut self, index: usize) -> io::Result<&[u8]> {
+        if self.cached.as_ref().map(|(i, _)| *i) != Some(index) {
+            let entry = self.table.entries[index];
+            let frame = decode_frame(&mut self.inner, &entry)?;
+            self.cached = Some((index, frame));
+        }
+        Ok(&self.cached.as_ref().expect("frame just cached").1)
+    }
+}
+
+impl<R: Read + Seek> Read for SeekableReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let Some(index) = self.table.frame_at(self.position) else {
+            return Ok(0);
+        };
+        let start = (self.position - self.table.entries[index].decompressed_offset) as usize;
+        let frame = self.load_frame(index)?;
+        let len = buf.len().min(frame.len() - start);
+        buf[..len].copy_from_slice(&frame[start..start + len]);
+        self.position += len as u64;
+        Ok(len)
+    }
+}
+
+impl<R: Read + Seek> Seek for SeekableReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(delta) => self.len().checked_add_signed(delta),
+            SeekFrom::Current(delta) => self.position.checked_add_signed(delta),
+        };
+        let target = target.ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "seek to a negative position")
+        })?;
+        self.position = target;
+        Ok(target)
+    }
+}
+
+/// Reads and decodes a single frame described by `entry`, checking that it
+/// decodes to exactly the size the seek table promised.
+pub fn decode_frame<R: Read + Seek>(reader: &mut R, entry: &SeekEntry) -> io::Result<Vec<u8>> {
+    let mut compressed = vec![0u8; entry.compressed_size as usize];
+    reader.seek(SeekFrom::Start(entry.compressed_offset))?;
+    reader.read_exact(&mut compressed)?;
+
+    // Cap the output at one byte past the promised size so a lying table
+    // can't make us inflate an arbitrary amount of data.
+    let mut frame = Vec::new();
+    Decoder::new(&compressed[..])?
+        .take(entry.decompressed_size as u64 + 1)
+        .read_to_end(&mut frame)?;
+    if frame.len() != entry.decompressed_size as usize {
+        return Err(invalid_data("frame size does not match the seek table"));
+    }
+    Ok(frame)
+}
+
+struct CountingWriter<W> {
+    inner: W,
+    count: u64,
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.count += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+// Size of a seek table's skippable frame holding `entries` frames.
+fn skippable_len(entries: usize, has_checksums: bool) -> u64 {
+    let entry_len = if has_checksums { 12 } else { 8 };
+    SKIPPABLE_HEADER_LEN + entries as u64 * entry_len + FOOTER_LEN
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes(bytes.try_into().expect("slice of four bytes"))
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn sample_data() -> Vec<u8> {
+        (0..50_000u32)
+            .flat_map(|i| (i % 251).to_le_bytes())
+            .collect()
+    }
+
+    // Random-access reads through SeekableReader must match slicing the original.
+    #[test]
+    fn seekable_reader_matches_original_slices() {
+        let data = sample_data();
+        let mut writer = SeekableWriter::new(Vec::new(), 3, 7_000).unwrap();
+        writer.write_all(&data).unwrap();
+        let compressed = writer.finish().unwrap();
+
+        let mut reader = SeekableReader::new(Cursor::new(compressed)).unwrap();
+        assert_eq!(reader.len(), data.len() as u64);
+        assert_eq!(
+            reader.seek_table().entries.len(),
+            data.len().div_ceil(7_000)
+        );
+
+        for (start, len) in [(0, 10), (6_995, 20), (123_456, 50_000), (199_990, 10)] {
+            let mut slice = Vec::new();
+            reader.seek(SeekFrom::Start(start)).unwrap();
+            (&mut reader).take(len).read_to_end(&mut slice).unwrap();
+            assert_eq!(slice, data[start as usize..(start + len) as usize]);
+        }
+    }
+
+    // The seek table lives in a skippable frame, so plain zstd still decodes the file.
+    #[test]
+    fn seekable_output_is_plain_zstd() {
+        let data = sample_data();
+        let mut writer = SeekableWriter::new(Vec::new(), 1, 64 * 1024).unwrap();
+        writer.write_all(&data).unwrap();
+        let compressed = writer.finish().unwrap();
+
+        assert_eq!(zstd::decode_all(&compressed[..]).unwrap(), data);
+        assert!(SeekTable::read_from(&mut Cursor::new(&data)).is_err());
+
+        // A table read with checksums is written back without them
+        let mut table = SeekTable::read_from(&mut Cursor::new(&compressed)).unwrap();
+        table.has_checksums = true;
+        let mut rewritten = compressed[..table.compressed_len() as usize].to_vec();
+        table.write_to(&mut rewritten).unwrap();
+        assert_eq!(rewritten, compressed);
+    }
+}
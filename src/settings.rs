@@ -0,0 +1,327 @@
+//! The settings screen: every `Config` option in one list, changed with the
+//! arrow keys and saved to the config file as soon as it changes.
+
+use crate::batch::CompressedPolicy;
+use crate::config::Config;
+use crate::output::{DEFAULT_TEMPLATE, validate_template};
+use crate::theme;
+use crate::{Algorithm, CompressionLevel, OverwritePolicy};
+use std::path::PathBuf;
+
+/// More threads than this is almost certainly a typo.
+pub const MAX_THREADS: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Setting {
+    Level,
+    Algorithm,
+    Threads,
+    Checksum,
+    LongDistance,
+    OutputDir,
+    OutputTemplate,
+    KeepSource,
+    Overwrite,
+    Verify,
+    OnCompressed,
+    AutoExit,
+    Theme,
+}
+
+impl Setting {
+    pub const ALL: [Setting; 13] = [
+        Setting::Level,
+        Setting::Algorithm,
+        Setting::Threads,
+        Setting::Checksum,
+        Setting::LongDistance,
+        Setting::OutputDir,
+        Setting::OutputTemplate,
+        Setting::KeepSource,
+        Setting::Overwrite,
+        Setting::Verify,
+        Setting::OnCompressed,
+        Setting::AutoExit,
+        Setting::Theme,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Setting::Level => "Level",
+            Setting::Algorithm => "Algorithm",
+            Setting::Threads => "Decompression threads",
+            Setting::Checksum => "Checksum",
+            Setting::LongDistance => "Long mode",
+            Setting::OutputDir => "Output directory",
+            Setting::OutputTemplate => "File name template",
+            Setting::KeepSource => "Keep source",
+            Setting::Overwrite => "Overwrite existing",
+            Setting::Verify => "Verify after compress",
+            Setting::OnCompressed => "Already compressed (folders)",
+            Setting::AutoExit => "Exit after a job",
+            Setting::Theme => "Theme",
+        }
+    }
+
+    pub fn value(self, config: &Config) -> String {
+        let on_off = |on: bool| if on { "on" } else { "off" }.to_string();
+        match self {
+            Setting::Level => config.level.label().to_string(),
+            Setting::Algorithm => config.algorithm.label().to_string(),
+            Setting::Threads => match config.threads {
+                0 => "auto (one per core)".to_string(),
+                n => n.to_string(),
+            },
+            Setting::Checksum => on_off(config.checksum),
+            Setting::LongDistance => on_off(config.long_distance),
+            Setting::OutputDir => match &config.output_dir {
+                Some(dir) => dir.display().to_string(),
+                None => "next to the input".to_string(),
+            },
+            Setting::OutputTemplate => config
+                .output_template
+                .clone()
+                .unwrap_or_else(|| DEFAULT_TEMPLATE.to_string()),
+            Setting::KeepSource => on_off(!config.remove_source),
+            Setting::Overwrite => config.overwrite.label().to_string(),
+            Setting::Verify => on_off(config.verify),
+            Setting::OnCompressed => config.on_compressed.label().to_string(),
+            Setting::AutoExit => on_off(config.auto_exit),
+            Setting::Theme => match &config.theme {
+                Some(theme) => theme.clone(),
+                None => "auto (dark, monochrome with NO_COLOR)".to_string(),
+            },
+        }
+    }
+
+    /// Edited as text with Enter rather than stepped with ←/→.
+    pub fn is_text(self) -> bool {
+        matches!(self, Setting::OutputDir | Setting::OutputTemplate)
+    }
+
+    /// Steps the value forward (→) or back (←). Text settings are left alone:
+    /// they only change through the editor, which shows why text is refused.
+    pub fn adjust(self, config: &mut Config, forward: bool) -> Result<(), String> {
+        match self {
+            Setting::Level => config.level = cycle(&CompressionLevel::ALL, config.level, forward),
+            Setting::Algorithm => {
+                config.algorithm = cycle(
+                    &[Algorithm::Zstd, Algorithm::Store],
+                    config.algorithm,
+                    forward,
+                )
+            }
+            Setting::Threads => {
+                config.threads = match (config.threads, forward) {
+                    (MAX_THREADS, true) => {
+                        return Err(format!("at most {} threads", MAX_THREADS));
+                    }
+                    (n, true) => n + 1,
+                    (n, false) => n.saturating_sub(1),
+                }
+            }
+            Setting::Checksum => config.checksum = !config.checksum,
+            Setting::LongDistance => config.long_distance = !config.long_distance,
+            Setting::OutputDir | Setting::OutputTemplate => {}
+            Setting::KeepSource => config.remove_source = !config.remove_source,
+            Setting::Overwrite => {
+                config.overwrite = cycle(
+                    &[OverwritePolicy::Always, OverwritePolicy::Never],
+                    config.overwrite,
+                    forward,
+                )
+            }
+            Setting::Verify => config.verify = !config.verify,
+            Setting::OnCompressed => {
+                config.on_compressed = cycle(
+                    &[
+                        CompressedPolicy::Skip,
+                        CompressedPolicy::Store,
+                        CompressedPolicy::Compress,
+                    ],
+                    config.on_compressed,
+                    forward,
+                )
+            }
+            Setting::AutoExit => config.auto_exit = !config.auto_exit,
+            Setting::Theme => {
+                // Unset (auto) comes before every named theme
+                let mut names: Vec<Option<String>> = vec![None];
+                names.extend(theme::names(&config.themes).into_iter().map(Some));
+                config.theme = cycle(&names, config.theme.clone(), forward);
+            }
+        }
+        Ok(())
+    }
+
+    /// Text currently stored for a text setting, used to prefill the editor.
+    pub fn text(self, config: &Config) -> String {
+        match self {
+            Setting::OutputDir => config
+                .output_dir
+                .as_ref()
+                .map(|dir| dir.display().to_string())
+                .unwrap_or_default(),
+            Setting::OutputTemplate => self.value(config),
+            _ => String::new(),
+        }
+    }
+
+    /// Validates and stores edited text.
+    pub fn set_text(self, config: &mut Config, text: &str) -> Result<(), String> {
+        match self {
+            Setting::OutputDir => config.output_dir = parse_output_dir(text)?,
+            Setting::OutputTemplate => {
+                let text = text.trim();
+                validate_template(text)?;
+                // Storing the default would pin it in the config file for no reason
+                config.output_template = (text != DEFAULT_TEMPLATE).then(|| text.to_string());
+            }
+            _ => unreachable!("{} is not a text setting", self.label()),
+        }
+        Ok(())
+    }
+
+    /// Copies this setting's value from `from` into `to`, leaving the rest of
+    /// `to` alone.
+    pub fn copy(self, from: &Config, to: &mut Config) {
+        match self {
+            Setting::Level => to.level = from.level,
+            Setting::Algorithm => to.algorithm = from.algorithm,
+            Setting::Threads => to.threads = from.threads,
+            Setting::Checksum => to.checksum = from.checksum,
+            Setting::LongDistance => to.long_distance = from.long_distance,
+            Setting::OutputDir => to.output_dir = from.output_dir.clone(),
+            Setting::OutputTemplate => to.output_template = from.output_template.clone(),
+            Setting::KeepSource => to.remove_source = from.remove_source,
+            Setting::Overwrite => to.overwrite = from.overwrite,
+            Setting::Verify => to.verify = from.verify,
+            Setting::OnCompressed => to.on_compressed = from.on_compressed,
+            Setting::AutoExit => to.auto_exit = from.auto_exit,
+            Setting::Theme => to.theme = from.theme.clone(),
+        }
+    }
+}
+
+/// State of the settings panel.
+#[derive(Debug, Default)]
+pub struct SettingsView {
+    pub selected: usize,
+    /// Text being typed for the selected setting, until Enter or Esc.
+    pub editing: Option<String>,
+    /// Why the last change was rejected, shown under the list.
+    pub error: Option<String>,
+}
+
+impl SettingsView {
+    pub fn setting(&self) -> Setting {
+        Setting::ALL[self.selected]
+    }
+}
+
+/// Empty clears the output dir. A missing directory is fine, jobs create it,
+/// but an existing file is not.
+pub fn parse_output_dir(text: &str) -> Result<Option<PathBuf>, String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Ok(None);
+    }
+    let path = PathBuf::from(text);
+    if path.exists() && !path.is_dir() {
+        return Err(format!("{} is a file, not a directory", text));
+    }
+    Ok(Some(path))
+}
+
+fn cycle<T: Clone + PartialEq>(all: &[T], current: T, forward: bool) -> T {
+    let index = all.iter().position(|v| *v == current).unwrap_or(0);
+    let next = if forward {
+        (index + 1) % all.len()
+    } else {
+        (index + all.len() - 1) % all.len()
+    };
+    all[next].clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adjusts_and_validates_settings() {
+        let mut config = Config::default();
+        Setting::Level.adjust(&mut config, true).unwrap();
+        assert_eq!(config.level, CompressionLevel::Best);
+        Setting::Level.adjust(&mut config, true).unwrap();
+        assert_eq!(config.level, CompressionLevel::Fast);
+        Setting::Level.adjust(&mut config, false).unwrap();
+        assert_eq!(config.level, CompressionLevel::Best);
+
+        Setting::KeepSource.adjust(&mut config, true).unwrap();
+        assert!(config.remove_source);
+        assert_eq!(Setting::KeepSource.value(&config), "off");
+
+        // Arrows don't touch text settings
+        config.output_dir = Some(PathBuf::from("out"));
+        Setting::OutputDir.adjust(&mut config, true).unwrap();
+        assert_eq!(config.output_dir, Some(PathBuf::from("out")));
+
+        Setting::Threads.adjust(&mut config, false).unwrap();
+        assert_eq!(config.threads, 0);
+        config.threads = MAX_THREADS;
+        assert!(Setting::Threads.adjust(&mut config, true).is_err());
+        assert_eq!(config.threads, MAX_THREADS);
+
+        let dir = std::env::temp_dir();
+        Setting::OutputDir
+            .set_text(&mut config, &dir.to_string_lossy())
+            .unwrap();
+        assert_eq!(config.output_dir, Some(dir.clone()));
+        // Missing directories are created by the job; files can't become one
+        let file = dir.join("freya_test_settings_file");
+        std::fs::write(&file, b"not a directory").unwrap();
+        assert!(
+            Setting::OutputDir
+                .set_text(&mut config, &file.to_string_lossy())
+                .is_err()
+        );
+        assert_eq!(config.output_dir, Some(dir.clone()));
+        std::fs::remove_file(&file).ok();
+        let missing = dir.join("freya_test_settings_missing");
+        Setting::OutputDir
+            .set_text(&mut config, &missing.to_string_lossy())
+            .unwrap();
+        assert_eq!(config.output_dir, Some(missing));
+        Setting::OutputDir.set_text(&mut config, "  ").unwrap();
+        assert_eq!(config.output_dir, None);
+
+        assert!(
+            Setting::OutputTemplate
+                .set_text(&mut config, "{stem}/{ext}")
+                .is_err()
+        );
+        Setting::OutputTemplate
+            .set_text(&mut config, "{stem}-{date}.{ext}.zst")
+            .unwrap();
+        assert_eq!(
+            Setting::OutputTemplate.value(&config),
+            "{stem}-{date}.{ext}.zst"
+        );
+        Setting::OutputTemplate
+            .set_text(&mut config, DEFAULT_TEMPLATE)
+            .unwrap();
+        assert_eq!(config.output_template, None);
+
+        Setting::Theme.adjust(&mut config, true).unwrap();
+        assert_eq!(config.theme.as_deref(), Some("dark"));
+        Setting::Theme.adjust(&mut config, false).unwrap();
+        Setting::Theme.adjust(&mut config, false).unwrap();
+        assert_eq!(config.theme.as_deref(), Some("monochrome"));
+
+        // Only the one setting reaches the file's config
+        let mut file_config = Config::default();
+        Setting::Theme.copy(&config, &mut file_config);
+        assert_eq!(file_config.theme.as_deref(), Some("monochrome"));
+        assert!(!file_config.remove_source);
+    }
+}
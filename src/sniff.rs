@@ -0,0 +1,138 @@
+//! Recognises content that is already compressed, where running zstd over it
+//! again mostly burns CPU time.
+
+/// Formats whose payload is already compressed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompressedFormat {
+    Zstd,
+    Gzip,
+    Bzip2,
+    Xz,
+    Lz4,
+    Zip,
+    SevenZip,
+    Rar,
+    Jpeg,
+    Png,
+    Gif,
+    Webp,
+    Mp4,
+    Matroska,
+    Mp3,
+    Ogg,
+    Flac,
+}
+
+impl CompressedFormat {
+    pub fn name(self) -> &'static str {
+        match self {
+            CompressedFormat::Zstd => "zstd",
+            CompressedFormat::Gzip => "gzip",
+            CompressedFormat::Bzip2 => "bzip2",
+            CompressedFormat::Xz => "xz",
+            CompressedFormat::Lz4 => "lz4",
+            CompressedFormat::Zip => "zip",
+            CompressedFormat::SevenZip => "7z",
+            CompressedFormat::Rar => "rar",
+            CompressedFormat::Jpeg => "JPEG",
+            CompressedFormat::Png => "PNG",
+            CompressedFormat::Gif => "GIF",
+            CompressedFormat::Webp => "WebP",
+            CompressedFormat::Mp4 => "MP4/MOV",
+            CompressedFormat::Matroska => "MKV/WebM",
+            CompressedFormat::Mp3 => "MP3",
+            CompressedFormat::Ogg => "Ogg",
+            CompressedFormat::Flac => "FLAC",
+        }
+    }
+}
+
+/// Identifies a compressed format from the first bytes of a file.
+pub fn sniff(header: &[u8]) -> Option<CompressedFormat> {
+    const MAGICS: [(&[u8], CompressedFormat); 17] = [
+        (&[0x28, 0xB5, 0x2F, 0xFD], CompressedFormat::Zstd),
+        (&[0x1F, 0x8B], CompressedFormat::Gzip),
+        (b"BZh", CompressedFormat::Bzip2),
+        (&[0xFD, b'7', b'z', b'X', b'Z', 0x00], CompressedFormat::Xz),
+        (&[0x04, 0x22, 0x4D, 0x18], CompressedFormat::Lz4),
+        (b"PK\x03\x04", CompressedFormat::Zip),
+        (
+            &[b'7', b'z', 0xBC, 0xAF, 0x27, 0x1C],
+            CompressedFormat::SevenZip,
+        ),
+        (b"Rar!\x1A\x07", CompressedFormat::Rar),
+        (&[0xFF, 0xD8, 0xFF], CompressedFormat::Jpeg),
+        (b"\x89PNG\r\n\x1A\n", CompressedFormat::Png),
+        (b"GIF8", CompressedFormat::Gif),
+        (&[0x1A, 0x45, 0xDF, 0xA3], CompressedFormat::Matroska),
+        (b"ID3", CompressedFormat::Mp3),
+        (&[0xFF, 0xFB], CompressedFormat::Mp3),
+        (&[0xFF, 0xF3], CompressedFormat::Mp3),
+        (b"OggS", CompressedFormat::Ogg),
+        (b"fLaC", CompressedFormat::Flac),
+    ];
+
+    if let Some((_, format)) = MAGICS.iter().find(|(magic, _)| header.starts_with(magic)) {
+        return Some(*format);
+    }
+    // These two carry their signature a few bytes in
+    if header.len() >= 12 && &header[0..4] == b"RIFF" && &header[8..12] == b"WEBP" {
+        return Some(CompressedFormat::Webp);
+    }
+    if header.len() >= 8 && &header[4..8] == b"ftyp" {
+        return Some(CompressedFormat::Mp4);
+    }
+    None
+}
+
+/// Shannon entropy in bits per byte: close to 8.0 for random or already
+/// compressed data, around 4-5 for typical text.
+pub fn entropy(data: &[u8]) -> f64 {
+    if data.is_empty() {
+        return 0.0;
+    }
+    let mut counts = [0u64; 256];
+    for &byte in data {
+        counts[byte as usize] += 1;
+    }
+    let len = data.len() as f64;
+    counts
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / len;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sniffs_magic_bytes_and_entropy() {
+        assert_eq!(
+            sniff(b"\xFF\xD8\xFF\xE0\x00\x10JFIF"),
+            Some(CompressedFormat::Jpeg)
+        );
+        assert_eq!(
+            sniff(b"\x00\x00\x00\x18ftypmp42"),
+            Some(CompressedFormat::Mp4)
+        );
+        assert_eq!(
+            sniff(b"RIFF\x24\x00\x00\x00WEBPVP8 "),
+            Some(CompressedFormat::Webp)
+        );
+        assert_eq!(
+            sniff(&zstd::bulk::compress(b"x", 1).unwrap()),
+            Some(CompressedFormat::Zstd)
+        );
+        assert_eq!(sniff(b"plain old text"), None);
+        assert_eq!(sniff(b""), None);
+
+        let every_byte: Vec<u8> = (0..=255u8).cycle().take(256 * 64).collect();
+        assert!((entropy(&every_byte) - 8.0).abs() < 1e-9);
+        assert_eq!(entropy(&[b'a'; 1000]), 0.0);
+    }
+}
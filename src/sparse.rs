@@ -0,0 +1,149 @@
+//! Sparse output for decompression. Whole filesystem blocks of zeros are
+//! skipped instead of written, so a disk image with large empty regions
+//! decompresses faster and only takes the space its data needs. On filesystems
+//! without holes the skipped blocks read back as zeros all the same.
+
+use std::fs::File;
+use std::io::{self, Seek, SeekFrom, Write};
+use std::ops::Range;
+
+/// The block size of common filesystems; a hole smaller than a block saves nothing.
+pub const BLOCK_SIZE: usize = 4096;
+
+/// The parts of `data` that have to be written when it lands at `offset` in
+/// the file: all of it but the aligned, whole blocks that are only zeros.
+pub fn data_ranges(data: &[u8], offset: u64) -> Vec<Range<usize>> {
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+    let mut start = 0;
+    while start < data.len() {
+        let to_boundary = BLOCK_SIZE - ((offset + start as u64) % BLOCK_SIZE as u64) as usize;
+        let end = (start + to_boundary).min(data.len());
+        let block = &data[start..end];
+        let hole = block.len() == BLOCK_SIZE && block.iter().all(|&b| b == 0);
+        if !hole {
+            match ranges.last_mut() {
+                Some(last) if last.end == start => last.end = end,
+                _ => ranges.push(start..end),
+            }
+        }
+        start = end;
+    }
+    ranges
+}
+
+/// Writes a file from the start, seeking over zero blocks. A block split
+/// across writes is held back until it is complete, so holes don't depend on
+/// how the data arrives. `finish` writes what is held back and sets the length,
+/// which a trailing hole would otherwise leave short.
+pub struct SparseWriter<'a> {
+    file: &'a File,
+    /// Bytes written or skipped so far, not counting `pending`.
+    position: u64,
+    /// Where the file's cursor is.
+    cursor: u64,
+    /// The start of an incomplete block.
+    pending: Vec<u8>,
+}
+
+impl<'a> SparseWriter<'a> {
+    pub fn new(file: &'a File) -> Self {
+        Self {
+            file,
+            position: 0,
+            cursor: 0,
+            pending: Vec::with_capacity(BLOCK_SIZE),
+        }
+    }
+
+    // Writes `data` at `position`, which is block-aligned except for the tail.
+    fn put(&mut self, data: &[u8]) -> io::Result<()> {
+        for range in data_ranges(data, self.position) {
+            let at = self.position + range.start as u64;
+            if at != self.cursor {
+                self.file.seek(SeekFrom::Start(at))?;
+            }
+            self.file.write_all(&data[range.clone()])?;
+            self.cursor = at + range.len() as u64;
+        }
+        self.position += data.len() as u64;
+        Ok(())
+    }
+
+    /// Returns the length of the file.
+    pub fn finish(mut self) -> io::Result<u64> {
+        let pending = std::mem::take(&mut self.pending);
+        self.put(&pending)?;
+        self.file.set_len(self.position)?;
+        Ok(self.position)
+    }
+}
+
+impl Write for SparseWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut rest = buf;
+        if !self.pending.is_empty() {
+            let missing = (BLOCK_SIZE - self.pending.len()).min(rest.len());
+            self.pending.extend_from_slice(&rest[..missing]);
+            rest = &rest[missing..];
+            if self.pending.len() < BLOCK_SIZE {
+                return Ok(buf.len());
+            }
+            let block = std::mem::take(&mut self.pending);
+            self.put(&block)?;
+            self.pending = block;
+            self.pending.clear();
+        }
+        let whole = rest.len() - rest.len() % BLOCK_SIZE;
+        self.put(&rest[..whole])?;
+        self.pending.extend_from_slice(&rest[whole..]);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skips_zero_blocks_and_keeps_the_length() {
+        let block = BLOCK_SIZE;
+        let mut data = vec![0u8; 64 * block + 100];
+        data[10] = 1;
+        data[5 * block..6 * block].fill(7);
+        data[64 * block + 50] = 9;
+        // Only whole, aligned zero blocks are left out
+        assert_eq!(
+            data_ranges(&data, 0),
+            [0..block, 5 * block..6 * block, 64 * block..64 * block + 100]
+        );
+        // Zeros straddling two blocks fill neither
+        let unaligned = data_ranges(&[0; BLOCK_SIZE], 1);
+        assert_eq!((unaligned.len(), unaligned[0].clone()), (1, 0..block));
+
+        let dir = std::env::temp_dir().join("freya_test_sparse");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("image.bin");
+        // Arriving in odd pieces, and ending on a hole
+        let data = &data[..63 * block];
+        let file = File::create(&path).unwrap();
+        let mut writer = SparseWriter::new(&file);
+        for piece in data.chunks(3000) {
+            writer.write_all(piece).unwrap();
+        }
+        assert_eq!(writer.finish().unwrap(), data.len() as u64);
+        assert_eq!(std::fs::read(&path).unwrap(), data);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            // st_blocks counts 512-byte units: two blocks of data, plus some slack
+            let allocated = std::fs::metadata(&path).unwrap().blocks() * 512;
+            assert!(allocated <= 2 * block as u64 + 8192, "{} bytes", allocated);
+        }
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
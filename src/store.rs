@@ -0,0 +1,179 @@
+//! Writes a zstd frame made only of raw (uncompressed) blocks. Costs no more than
+//! a copy, yet any zstd decoder reads it back, so already-compressed files can go
+//! through the same pipeline as everything else.
+
+use crate::frames::ZSTD_MAGIC;
+use std::fs::File;
+use std::io::{self, Read, Write};
+
+/// Largest block the format allows.
+pub const MAX_BLOCK_SIZE: usize = 128 * 1024;
+// Window_Descriptor for a 128 KiB window: exponent 7, mantissa 0 (2^(10+7)).
+// Raw blocks never reference earlier data, so the decoder needs no more than one block.
+const WINDOW_DESCRIPTOR: u8 = 7 << 3;
+const RAW_BLOCK: u32 = 0;
+
+pub struct StoreWriter<W: Write> {
+    inner: W,
+    buffer: Vec<u8>,
+    pledged_size: Option<u64>,
+    written: u64,
+}
+
+impl<W: Write> StoreWriter<W> {
+    /// `pledged_size` is recorded in the frame header when known; `finish` then
+    /// fails if a different number of bytes was written.
+    pub fn new(mut inner: W, pledged_size: Option<u64>) -> io::Result<Self> {
+        write_frame_header(&mut inner, pledged_size)?;
+        Ok(Self {
+            inner,
+            buffer: Vec::with_capacity(MAX_BLOCK_SIZE),
+            pledged_size,
+            written: 0,
+        })
+    }
+
+    fn write_block(&mut self, last: bool) -> io::Result<()> {
+        self.inner
+            .write_all(&block_header(self.buffer.len(), last))?;
+        self.inner.write_all(&self.buffer)?;
+        self.buffer.clear();
+        Ok(())
+    }
+
+    /// Writes the last block and returns the inner writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        if let Some(pledged) = self.pledged_size
+            && pledged != self.written
+        {
+            return Err(changed_size(pledged, self.written));
+        }
+        // The last block may be empty, which is how an empty input is stored
+        self.write_block(true)?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for StoreWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // A full block is only written once more data arrives, because the last
+        // block has to carry the Last_Block flag and we can't know in advance.
+        if self.buffer.len() == MAX_BLOCK_SIZE && !buf.is_empty() {
+            self.write_block(false)?;
+        }
+        let len = buf.len().min(MAX_BLOCK_SIZE - self.buffer.len());
+        self.buffer.extend_from_slice(&buf[..len]);
+        self.written += len as u64;
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Stores `len` bytes of `input`, from its current position, exactly as
+/// `StoreWriter` would, but the kernel copies each block from file to file
+/// (`copy_file_range` or `sendfile` on Linux, a plain copy elsewhere). `progress`
+/// gets the bytes stored so far after each block.
+pub fn store_file(
+    input: &mut File,
+    mut output: File,
+    len: u64,
+    mut progress: impl FnMut(u64) -> io::Result<()>,
+) -> io::Result<File> {
+    write_frame_header(&mut output, Some(len))?;
+    let mut stored = 0;
+    loop {
+        let block = (len - stored).min(MAX_BLOCK_SIZE as u64);
+        let last = stored + block == len;
+        output.write_all(&block_header(block as usize, last))?;
+        let copied = io::copy(&mut input.take(block), &mut output)?;
+        if copied < block {
+            return Err(changed_size(len, stored + copied));
+        }
+        stored += block;
+        progress(stored)?;
+        if last {
+            return Ok(output);
+        }
+    }
+}
+
+fn write_frame_header(inner: &mut impl Write, pledged_size: Option<u64>) -> io::Result<()> {
+    inner.write_all(&ZSTD_MAGIC.to_le_bytes())?;
+    match pledged_size {
+        // Frame_Content_Size_flag 3: an 8-byte content size follows the window descriptor
+        Some(size) => {
+            inner.write_all(&[0b1100_0000, WINDOW_DESCRIPTOR])?;
+            inner.write_all(&size.to_le_bytes())
+        }
+        None => inner.write_all(&[0, WINDOW_DESCRIPTOR]),
+    }
+}
+
+fn block_header(len: usize, last: bool) -> [u8; 3] {
+    let header = (len as u32) << 3 | RAW_BLOCK << 1 | last as u32;
+    let [a, b, c, _] = header.to_le_bytes();
+    [a, b, c]
+}
+
+fn changed_size(expected: u64, written: u64) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!(
+            "input changed size while storing ({} bytes expected, {} written)",
+            expected, written
+        ),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stored_frames_decode_with_zstd() {
+        let dir = std::env::temp_dir().join("freya_test_store");
+        std::fs::create_dir_all(&dir).unwrap();
+        let (input_path, output_path) = (dir.join("input.bin"), dir.join("output.zst"));
+        for len in [
+            0,
+            1,
+            MAX_BLOCK_SIZE,
+            MAX_BLOCK_SIZE + 1,
+            3 * MAX_BLOCK_SIZE + 17,
+        ] {
+            let data: Vec<u8> = (0..len).map(|i| (i * 31 % 256) as u8).collect();
+            let mut writer = StoreWriter::new(Vec::new(), Some(len as u64)).unwrap();
+            writer.write_all(&data).unwrap();
+            let stored = writer.finish().unwrap();
+            assert_eq!(zstd::decode_all(&stored[..]).unwrap(), data, "len {}", len);
+
+            // The kernel copy writes the very same bytes
+            std::fs::write(&input_path, &data).unwrap();
+            let mut input = File::open(&input_path).unwrap();
+            let output = File::create(&output_path).unwrap();
+            let mut reports = Vec::new();
+            store_file(&mut input, output, len as u64, |done| {
+                reports.push(done);
+                Ok(())
+            })
+            .unwrap();
+            assert_eq!(std::fs::read(&output_path).unwrap(), stored, "len {}", len);
+            assert_eq!(reports.last(), Some(&(len as u64)));
+        }
+
+        let mut writer = StoreWriter::new(Vec::new(), Some(10)).unwrap();
+        writer.write_all(b"short").unwrap();
+        assert!(writer.finish().is_err());
+        // A file shorter than promised
+        let output = File::create(&output_path).unwrap();
+        let mut input = File::open(&input_path).unwrap();
+        let len = input.metadata().unwrap().len();
+        assert!(store_file(&mut input, output, len + 10, |_| Ok(())).is_err());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
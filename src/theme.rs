@@ -0,0 +1,285 @@
+//! Colors of the TUI. Four built-in themes, custom ones from `[themes.<name>]`
+//! tables in the config, and `NO_COLOR` honoured when no theme is picked.
+
+use ratatui::style::{Color, Modifier, Style};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+pub const BUILT_IN: [&str; 4] = ["dark", "light", "high-contrast", "monochrome"];
+
+/// Every style the UI draws with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    /// Block borders.
+    pub border: Style,
+    /// Keys in the hints and the help overlay.
+    pub key: Style,
+    /// The status line, the chosen level, headings, the gauge and the charts.
+    pub highlight: Style,
+    /// The selected row of a list.
+    pub selected: Style,
+    /// Secondary text.
+    pub muted: Style,
+    /// Errors, failed jobs and anything destructive that is switched on.
+    pub error: Style,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::DARK
+    }
+}
+
+impl Theme {
+    /// The original look, for dark terminals.
+    pub const DARK: Theme = Theme {
+        border: Style::new().fg(Color::Blue),
+        key: Style::new().fg(Color::Blue).add_modifier(Modifier::BOLD),
+        highlight: Style::new().fg(Color::Yellow),
+        selected: Style::new()
+            .fg(Color::Yellow)
+            .add_modifier(Modifier::BOLD.union(Modifier::REVERSED)),
+        muted: Style::new().fg(Color::DarkGray),
+        error: Style::new().fg(Color::Red),
+    };
+
+    /// Yellow disappears on a white background; magenta and a blue selection don't.
+    pub const LIGHT: Theme = Theme {
+        border: Style::new().fg(Color::Blue),
+        key: Style::new().fg(Color::Blue).add_modifier(Modifier::BOLD),
+        highlight: Style::new().fg(Color::Magenta),
+        selected: Style::new()
+            .fg(Color::White)
+            .bg(Color::Blue)
+            .add_modifier(Modifier::BOLD),
+        muted: Style::new().fg(Color::DarkGray),
+        error: Style::new().fg(Color::Red),
+    };
+
+    /// Bright colors only, and errors told apart by more than their color.
+    pub const HIGH_CONTRAST: Theme = Theme {
+        border: Style::new().fg(Color::White),
+        key: Style::new()
+            .fg(Color::LightCyan)
+            .add_modifier(Modifier::BOLD),
+        highlight: Style::new()
+            .fg(Color::LightYellow)
+            .add_modifier(Modifier::BOLD),
+        selected: Style::new()
+            .fg(Color::Black)
+            .bg(Color::LightYellow)
+            .add_modifier(Modifier::BOLD),
+        muted: Style::new().fg(Color::White),
+        error: Style::new()
+            .fg(Color::LightMagenta)
+            .add_modifier(Modifier::BOLD.union(Modifier::UNDERLINED)),
+    };
+
+    /// No colors at all, only bold, dim, reversed and underlined text.
+    pub const MONOCHROME: Theme = Theme {
+        border: Style::new(),
+        key: Style::new().add_modifier(Modifier::BOLD),
+        highlight: Style::new().add_modifier(Modifier::BOLD),
+        selected: Style::new().add_modifier(Modifier::REVERSED),
+        muted: Style::new().add_modifier(Modifier::DIM),
+        error: Style::new().add_modifier(Modifier::BOLD.union(Modifier::UNDERLINED)),
+    };
+
+    pub fn built_in(name: &str) -> Option<Theme> {
+        match name {
+            "dark" => Some(Self::DARK),
+            "light" => Some(Self::LIGHT),
+            "high-contrast" => Some(Self::HIGH_CONTRAST),
+            "monochrome" => Some(Self::MONOCHROME),
+            _ => None,
+        }
+    }
+
+    /// A theme from `custom` (the config's `[themes]` tables) or a built-in one.
+    /// A custom table named like a built-in theme adjusts that theme.
+    pub fn named(name: &str, custom: &BTreeMap<String, ThemeSpec>) -> Result<Theme, String> {
+        match custom.get(name) {
+            Some(spec) => spec.resolve(name),
+            None => Self::built_in(name).ok_or_else(|| {
+                format!(
+                    "unknown theme '{}' (expected {} or a [themes.{}] table)",
+                    name,
+                    BUILT_IN.join(", "),
+                    name
+                )
+            }),
+        }
+    }
+
+    /// The theme the config picks; without one, dark unless `no_color` is set.
+    pub fn from_config(
+        theme: Option<&str>,
+        custom: &BTreeMap<String, ThemeSpec>,
+        no_color: bool,
+    ) -> Result<Theme, String> {
+        match theme {
+            Some(name) => Self::named(name, custom),
+            None if no_color => Ok(Self::MONOCHROME),
+            None => Ok(Self::DARK),
+        }
+    }
+}
+
+/// A `[themes.<name>]` table: a built-in `base` with some styles replaced. A
+/// style is a color, optionally `on` a background color, and modifiers, e.g.
+/// `"black on light-cyan bold"`. Colors are names, `#rrggbb` or 0-255.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThemeSpec {
+    /// Defaults to the theme's own name if that is built in, otherwise dark.
+    pub base: Option<String>,
+    pub border: Option<String>,
+    pub key: Option<String>,
+    pub highlight: Option<String>,
+    pub selected: Option<String>,
+    pub muted: Option<String>,
+    pub error: Option<String>,
+}
+
+impl ThemeSpec {
+    fn resolve(&self, name: &str) -> Result<Theme, String> {
+        let base = self
+            .base
+            .as_deref()
+            .unwrap_or(if Theme::built_in(name).is_some() {
+                name
+            } else {
+                "dark"
+            });
+        let mut theme = Theme::built_in(base).ok_or_else(|| {
+            format!(
+                "theme '{}': unknown base '{}' (expected {})",
+                name,
+                base,
+                BUILT_IN.join(", ")
+            )
+        })?;
+        let styles = [
+            ("border", &self.border, &mut theme.border),
+            ("key", &self.key, &mut theme.key),
+            ("highlight", &self.highlight, &mut theme.highlight),
+            ("selected", &self.selected, &mut theme.selected),
+            ("muted", &self.muted, &mut theme.muted),
+            ("error", &self.error, &mut theme.error),
+        ];
+        for (role, text, style) in styles {
+            if let Some(text) = text {
+                *style =
+                    parse_style(text).map_err(|e| format!("theme '{}': {}: {}", name, role, e))?;
+            }
+        }
+        Ok(theme)
+    }
+}
+
+/// Every theme name the config can pick, built-in ones first.
+pub fn names(custom: &BTreeMap<String, ThemeSpec>) -> Vec<String> {
+    let mut names: Vec<String> = BUILT_IN.iter().map(|name| name.to_string()).collect();
+    names.extend(
+        custom
+            .keys()
+            .filter(|name| Theme::built_in(name).is_none())
+            .cloned(),
+    );
+    names
+}
+
+/// `NO_COLOR` set to anything but the empty string, as <https://no-color.org> asks.
+pub fn no_color() -> bool {
+    std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty())
+}
+
+/// `"yellow"`, `"black on yellow bold"`, `"#ff8800 underlined"`...
+pub fn parse_style(text: &str) -> Result<Style, String> {
+    let color = |word: &str| {
+        word.parse::<Color>()
+            .map_err(|_| format!("unknown color '{}'", word))
+    };
+    let mut style = Style::new();
+    let mut words = text.split_whitespace();
+    while let Some(word) = words.next() {
+        style = match word.to_ascii_lowercase().as_str() {
+            "bold" => style.add_modifier(Modifier::BOLD),
+            "dim" => style.add_modifier(Modifier::DIM),
+            "italic" => style.add_modifier(Modifier::ITALIC),
+            "underlined" => style.add_modifier(Modifier::UNDERLINED),
+            "reversed" => style.add_modifier(Modifier::REVERSED),
+            "on" => {
+                let background = words
+                    .next()
+                    .ok_or_else(|| format!("'{}': expected a color after 'on'", text))?;
+                style.bg(color(background)?)
+            }
+            _ => style.fg(color(word)?),
+        };
+    }
+    Ok(style)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picks_and_customizes_themes() {
+        let none = BTreeMap::new();
+        assert_eq!(Theme::from_config(None, &none, false), Ok(Theme::DARK));
+        assert_eq!(Theme::from_config(None, &none, true), Ok(Theme::MONOCHROME));
+        // An explicit theme wins over NO_COLOR
+        assert_eq!(
+            Theme::from_config(Some("light"), &none, true),
+            Ok(Theme::LIGHT)
+        );
+        assert!(Theme::named("solarized", &none).is_err());
+
+        assert_eq!(
+            parse_style("black on light-cyan bold"),
+            Ok(Style::new()
+                .fg(Color::Black)
+                .bg(Color::LightCyan)
+                .add_modifier(Modifier::BOLD))
+        );
+        assert_eq!(
+            parse_style("#ff8800"),
+            Ok(Style::new().fg(Color::Rgb(255, 136, 0)))
+        );
+        assert!(parse_style("black on").is_err());
+        assert!(parse_style("blurple").is_err());
+
+        let custom: BTreeMap<String, ThemeSpec> = toml::from_str(
+            r#"
+            [dark]
+            border = "cyan"
+
+            [sunset]
+            base = "light"
+            highlight = "208 bold"
+            "#,
+        )
+        .unwrap();
+        let dark = Theme::named("dark", &custom).unwrap();
+        assert_eq!(dark.border, Style::new().fg(Color::Cyan));
+        assert_eq!(dark.key, Theme::DARK.key);
+        let sunset = Theme::named("sunset", &custom).unwrap();
+        assert_eq!(
+            sunset.highlight,
+            Style::new()
+                .fg(Color::Indexed(208))
+                .add_modifier(Modifier::BOLD)
+        );
+        assert_eq!(sunset.selected, Theme::LIGHT.selected);
+        assert_eq!(
+            names(&custom),
+            ["dark", "light", "high-contrast", "monochrome", "sunset"]
+        );
+
+        let broken: BTreeMap<String, ThemeSpec> =
+            toml::from_str("[mine]\nbase = \"neon\"").unwrap();
+        assert!(Theme::named("mine", &broken).is_err());
+    }
+}
@@ -1,20 +1,101 @@
-use crate::{app::App, CompressionLevel};
+use crate::app::{App, Benchmark, Panel};
+use crate::bench::LevelResult;
+use crate::config::Config;
+use crate::help::{Binding, Context};
+use crate::history::{self, HistoryEntry, HistoryView};
+use crate::info::FileInfo;
+use crate::keymap::{Action, Keymap};
+use crate::mouse::{Target, Targets};
+use crate::settings::{Setting, SettingsView};
+use crate::theme::Theme;
+use crate::{CompressionLevel, human_bytes};
 use ratatui::{
     buffer::Buffer,
-    layout::{Constraint, Layout, Rect},
-    style::{Style, Stylize},
+    layout::{Constraint, Layout, Margin, Rect},
+    style::{Styled, Stylize},
     symbols::border,
-    text::{Line, Text},
-    widgets::{Block, Gauge, Paragraph, Widget},
+    text::{Line, Span, Text},
+    widgets::{Bar, BarChart, Block, Clear, Gauge, LineGauge, Paragraph, Row, Table, Widget},
 };
+use std::path::PathBuf;
+
+/// How the screen is split, picked from the terminal size on every frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LayoutMode {
+    /// One unbordered line each for the level, status and progress.
+    Compact,
+    #[default]
+    Standard,
+    /// The standard screen beside panes for the job, recent jobs and the log.
+    Dashboard,
+}
+
+impl LayoutMode {
+    /// Smallest (width, height) the bordered blocks fit in.
+    const STANDARD_MIN: (u16, u16) = (60, 16);
+    /// Smallest (width, height) that leaves room for the side panes.
+    const DASHBOARD_MIN: (u16, u16) = (150, 36);
+
+    pub fn for_area(area: Rect) -> Self {
+        let fits = |(width, height): (u16, u16)| area.width >= width && area.height >= height;
+        if fits(Self::DASHBOARD_MIN) {
+            LayoutMode::Dashboard
+        } else if fits(Self::STANDARD_MIN) {
+            LayoutMode::Standard
+        } else {
+            LayoutMode::Compact
+        }
+    }
+}
 
 impl Widget for &mut App {
     fn render(self, area: Rect, buf: &mut Buffer) {
+        self.click_targets.clear();
+        self.layout = LayoutMode::for_area(area);
+        match self.layout {
+            LayoutMode::Compact => {
+                let rest = self.render_compact(area, buf);
+                self.render_panel(rest, buf);
+            }
+            LayoutMode::Standard => {
+                let rest = self.render_standard(area, buf);
+                self.render_panel(rest, buf);
+            }
+            LayoutMode::Dashboard => {
+                let [main, side] =
+                    Layout::horizontal([Constraint::Fill(3), Constraint::Fill(2)]).areas(area);
+                let rest = self.render_standard(main, buf);
+                self.render_panel(rest, buf);
+                self.render_side(side, buf);
+            }
+        }
+
+        if self.show_help {
+            let context = self.context();
+            render_help(
+                context,
+                &self.keymap,
+                &self.theme,
+                &mut self.click_targets,
+                area,
+                buf,
+            );
+        }
+    }
+}
+
+impl App {
+    // The bordered stack: title with the key hints, level selector, status and
+    // progress. Returns the space left below it.
+    fn render_standard(&mut self, area: Rect, buf: &mut Buffer) -> Rect {
+        let theme = self.theme;
         let mut constraints = vec![
             Constraint::Length(5), // Height for the description block (borders + text + padding)
             Constraint::Length(3), // Height for the compression level selector
-            Constraint::Length(3), // Height for the status / instruction block
         ];
+        // The status block grows a line to preview the output path of a pending job
+        let status_lines = self.status_lines();
+        constraints.push(Constraint::Length(status_lines.len() as u16 + 2));
 
         let show_progress = self.is_compressing || self.progress > 0.0;
         if show_progress {
@@ -26,20 +107,18 @@ impl Widget for &mut App {
 
         // --- Title / description block ---
         let title = Line::from(" Freya - Lossless Compression for files ".bold());
-        let instructions = Line::from(vec![
-            " Open File ".into(),
-            "<o>".blue().bold(),
-            " | Decompress ".into(),
-            "<d>".blue().bold(),
-            " | Level ".into(),
-            "<↑/↓>".blue().bold(),
-            " | Quit ".into(),
-            "<Q> ".blue().bold(),
-        ]);
+        // The footer follows what the keys currently act on
+        let instructions = footer(
+            self.context().hints(),
+            &self.keymap,
+            &theme,
+            bottom_row(chunks[0]),
+            &mut self.click_targets,
+        );
         let title_block = Block::bordered()
             .title(title.centered())
             .title_bottom(instructions.centered())
-            .border_style(Style::new().blue())
+            .border_style(theme.border)
             .border_set(border::DOUBLE);
 
         let description_text = Text::from(vec![Line::from(vec![
@@ -52,40 +131,29 @@ impl Widget for &mut App {
             .render(chunks[0], buf);
 
         // --- Compression level selector ---
-        let levels = [
-            CompressionLevel::Fast,
-            CompressionLevel::Normal,
-            CompressionLevel::Best,
-        ];
-        let level_line: Line = {
-            let mut spans = vec![" Level: ".into()];
-            for lvl in levels {
-                if lvl == self.compression_level {
-                    spans.push(format!(" [{}] ", lvl.label()).yellow().bold());
-                } else {
-                    spans.push(format!("  {}  ", lvl.label()).into());
-                }
-            }
-            spans.push("  ↑/↓ to change".dark_gray());
-            Line::from(spans)
-        };
+        let pieces = self.level_pieces();
+        // Drawn inside the border, from the top left
+        let inner = chunks[1].inner(Margin::new(1, 1));
+        place(
+            &pieces,
+            inner.x,
+            inner.y,
+            inner.right(),
+            &mut self.click_targets,
+        );
         let level_block = Block::bordered()
-            .border_style(Style::new().blue())
+            .border_style(theme.border)
             .border_set(border::DOUBLE);
-        Paragraph::new(Text::from(vec![level_line]))
+        Paragraph::new(line_of(pieces))
             .left_aligned()
             .block(level_block)
             .render(chunks[1], buf);
 
         // --- Status message ---
-        let status_text = Text::from(vec![Line::from(vec![self
-            .status_message
-            .to_string()
-            .yellow()])]);
         let status_block = Block::bordered()
-            .border_style(Style::new().blue())
+            .border_style(theme.border)
             .border_set(border::DOUBLE);
-        Paragraph::new(status_text)
+        Paragraph::new(Text::from(status_lines))
             .left_aligned()
             .block(status_block)
             .render(chunks[2], buf);
@@ -97,12 +165,989 @@ impl Widget for &mut App {
                 .block(
                     Block::bordered()
                         .title(" Progress ")
-                        .border_style(Style::default().blue()),
+                        .border_style(theme.border),
                 )
-                .gauge_style(Style::default().fg(ratatui::style::Color::Yellow))
+                .gauge_style(theme.highlight)
                 .ratio(self.progress.clamp(0.0, 1.0))
                 .label(format!("{}%", percentage));
             gauge.render(chunks[3], buf);
         }
+
+        chunks[chunks.len() - 1]
+    }
+
+    // No borders: the level, status and progress take a line each and the key
+    // hints move to the bottom line. Returns the space left in between.
+    fn render_compact(&mut self, area: Rect, buf: &mut Buffer) -> Rect {
+        let theme = self.theme;
+        let status_lines = self.status_lines();
+        let show_progress = self.is_compressing || self.progress > 0.0;
+        let [level_area, status_area, gauge_area, rest, footer_area] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Length(status_lines.len() as u16),
+            Constraint::Length(u16::from(show_progress)),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .areas(area);
+
+        let mut pieces = vec![(" Freya ".bold(), None), ("│".set_style(theme.muted), None)];
+        pieces.extend(self.level_pieces());
+        place(
+            &pieces,
+            level_area.x,
+            level_area.y,
+            level_area.right(),
+            &mut self.click_targets,
+        );
+        Paragraph::new(line_of(pieces)).render(level_area, buf);
+        Paragraph::new(Text::from(status_lines)).render(status_area, buf);
+
+        if show_progress {
+            let percentage = (self.progress * 100.0).clamp(0.0, 100.0) as u16;
+            LineGauge::default()
+                .filled_style(theme.highlight)
+                .unfilled_style(theme.muted)
+                .ratio(self.progress.clamp(0.0, 1.0))
+                .label(format!(" {:>3}%", percentage))
+                .render(gauge_area, buf);
+        }
+
+        let hints = footer(
+            self.context().hints(),
+            &self.keymap,
+            &theme,
+            footer_area,
+            &mut self.click_targets,
+        );
+        Paragraph::new(hints.centered()).render(footer_area, buf);
+        rest
+    }
+
+    // The info / benchmark / settings / history panel, filling `area` while open.
+    fn render_panel(&mut self, area: Rect, buf: &mut Buffer) {
+        let theme = self.theme;
+        let targets = &mut self.click_targets;
+        match &self.panel {
+            Some(Panel::Info(info)) => render_info(info, &self.keymap, &theme, targets, area, buf),
+            Some(Panel::Bench(bench)) => render_bench(
+                bench,
+                self.compression_level,
+                &self.keymap,
+                &theme,
+                targets,
+                area,
+                buf,
+            ),
+            Some(Panel::Settings(view)) => {
+                render_settings(view, &self.config, &self.keymap, &theme, targets, area, buf)
+            }
+            Some(Panel::History(view)) => {
+                render_history(view, &self.keymap, &theme, targets, area, buf)
+            }
+            None => {}
+        }
+    }
+
+    // The dashboard's side: the current job, the latest jobs and the log.
+    fn render_side(&self, area: Rect, buf: &mut Buffer) {
+        let [job_area, recent_area, log_area] = Layout::vertical([
+            Constraint::Length(8),
+            Constraint::Fill(1),
+            Constraint::Fill(1),
+        ])
+        .areas(area);
+        render_job(self, job_area, buf);
+        render_recent(self.recent.as_deref(), &self.theme, recent_area, buf);
+        render_log(&self.log, &self.theme, log_area, buf);
+    }
+
+    /// " Level: Fast [Normal] Best | Remove source: off", each part clickable.
+    fn level_pieces(&self) -> Vec<(Span<'static>, Option<Target>)> {
+        let theme = self.theme;
+        let toggle = Some(Target::Action(Action::ToggleRemoveSource));
+        let mut pieces = vec![(" Level: ".into(), None)];
+        for lvl in CompressionLevel::ALL {
+            let span = if lvl == self.compression_level {
+                format!(" [{}] ", lvl.label()).set_style(theme.highlight.bold())
+            } else {
+                format!("  {}  ", lvl.label()).into()
+            };
+            pieces.push((span, Some(Target::Level(lvl))));
+        }
+        pieces.push(("  ↑/↓ to change".set_style(theme.muted), None));
+        pieces.push(("  | ".into(), None));
+        pieces.push(("Remove source: ".into(), toggle));
+        if self.job_options.remove_source {
+            pieces.push(("on".set_style(theme.error.bold()), toggle));
+        } else {
+            pieces.push(("off".into(), toggle));
+        }
+        pieces.push((" <r>".set_style(theme.muted), toggle));
+        pieces
+    }
+
+    /// The status message, and the output path while a job waits for Enter.
+    fn status_lines(&self) -> Vec<Line<'static>> {
+        let theme = self.theme;
+        let mut lines = vec![Line::from(
+            self.status_message.to_string().set_style(theme.highlight),
+        )];
+        match self.pending_output() {
+            Some(Ok(path)) => lines.push(Line::from(vec![
+                " Output: ".set_style(theme.muted),
+                path.display().to_string().into(),
+            ])),
+            Some(Err(e)) => lines.push(Line::from(format!(" Output: {}", e)).style(theme.error)),
+            None => {}
+        }
+        lines
+    }
+}
+
+/// " Open File <o> | Folder <f> ", the format every footer uses. Each hint is
+/// also a button: the name does the binding's first action, each key its own.
+fn hint_pieces(
+    bindings: &[Binding],
+    keymap: &Keymap,
+    theme: &Theme,
+) -> Vec<(Span<'static>, Option<Target>)> {
+    let key = |label: String| label.set_style(theme.key);
+    let mut pieces = Vec::new();
+    for (index, binding) in bindings.iter().enumerate() {
+        let separator = if index == 0 { " " } else { " | " };
+        let first = binding.first_action().map(Target::Action);
+        pieces.push((separator.into(), None));
+        pieces.push((format!("{} ", binding.action).into(), first));
+        pieces.push((key("<".to_string()), first));
+        for (index, (label, action)) in binding.key_labels(keymap, false).into_iter().enumerate() {
+            if index > 0 {
+                pieces.push((key("/".to_string()), None));
+            }
+            pieces.push((key(label), action.map(Target::Action)));
+        }
+        pieces.push((key(">".to_string()), first));
+    }
+    pieces.push((" ".into(), None));
+    pieces
+}
+
+/// The hints to center on `row`, registered where they land. Hints that don't
+/// fit are dropped from the end, except the last two (Help and Quit, mostly).
+fn footer(
+    bindings: &[Binding],
+    keymap: &Keymap,
+    theme: &Theme,
+    row: Rect,
+    targets: &mut Targets,
+) -> Line<'static> {
+    let width = |pieces: &[(Span, Option<Target>)]| -> u16 {
+        pieces.iter().map(|(span, _)| span.width() as u16).sum()
+    };
+    let mut bindings = bindings.to_vec();
+    let mut pieces = hint_pieces(&bindings, keymap, theme);
+    while width(&pieces) > row.width && bindings.len() > 2 {
+        bindings.remove(bindings.len() - 3);
+        pieces = hint_pieces(&bindings, keymap, theme);
+    }
+    // Still too wide, it gets cut short; leave it unclickable then
+    if width(&pieces) <= row.width && row.height > 0 {
+        let x = row.x + (row.width - width(&pieces)) / 2;
+        place(&pieces, x, row.y, row.right(), targets);
+    }
+    line_of(pieces)
+}
+
+/// Where a bordered block draws its bottom title.
+fn bottom_row(area: Rect) -> Rect {
+    Rect {
+        x: area.x + 1,
+        y: area.bottom().saturating_sub(1),
+        width: area.width.saturating_sub(2),
+        height: area.height.min(1),
+    }
+}
+
+fn line_of(pieces: Vec<(Span<'static>, Option<Target>)>) -> Line<'static> {
+    Line::from(pieces.into_iter().map(|(span, _)| span).collect::<Vec<_>>())
+}
+
+/// Records the targets of a line of `pieces` drawn from `x`, `y`.
+fn place(pieces: &[(Span, Option<Target>)], x: u16, y: u16, right: u16, targets: &mut Targets) {
+    let widths: Vec<(u16, Option<Target>)> = pieces
+        .iter()
+        .map(|(span, target)| (span.width() as u16, *target))
+        .collect();
+    targets.push_row(x, y, right, &widths);
+}
+
+/// The registered rows of a list: `first` is the index of the row drawn at `area.y`.
+fn push_rows(area: Rect, first: usize, count: usize, targets: &mut Targets) {
+    for (line, index) in (first..count).enumerate().take(area.height as usize) {
+        let row = Rect::new(area.x, area.y + line as u16, area.width, 1);
+        targets.push(row, Target::Row(index));
+    }
+}
+
+// A centered popup over everything else, listing every binding by context.
+fn render_help(
+    context: Context,
+    keymap: &Keymap,
+    theme: &Theme,
+    targets: &mut Targets,
+    area: Rect,
+    buf: &mut Buffer,
+) {
+    let popup = area.centered(Constraint::Percentage(80), Constraint::Percentage(90));
+    Clear.render(popup, buf);
+    // Nothing underneath the overlay can be clicked
+    targets.clear();
+    let block = panel_block(
+        "Help: keys by context",
+        footer(context.hints(), keymap, theme, bottom_row(popup), targets),
+        theme,
+    );
+    let inner = block.inner(popup);
+    block.render(popup, buf);
+
+    let group = |context: Context| {
+        let mut lines =
+            vec![Line::from(format!(" {}", context.title())).style(theme.highlight.bold())];
+        lines.extend(context.bindings().iter().map(|binding| {
+            Line::from(vec![
+                format!("   {:<10} ", binding.keys_label(keymap, true)).set_style(theme.key),
+                binding.action.into(),
+            ])
+        }));
+        lines.push(Line::from(""));
+        lines
+    };
+    // Split where the left column holds about half the lines
+    let height = |context: &Context| context.bindings().len() + 2;
+    let half = Context::ALL.iter().map(height).sum::<usize>() / 2;
+    let mut split = 0;
+    let mut lines = 0;
+    while lines < half {
+        lines += height(&Context::ALL[split]);
+        split += 1;
+    }
+    let (left, right) = Context::ALL.split_at(split);
+    let [left_area, right_area] = Layout::horizontal([Constraint::Percentage(50); 2]).areas(inner);
+    Paragraph::new(Text::from(
+        left.iter().copied().flat_map(group).collect::<Vec<_>>(),
+    ))
+    .render(left_area, buf);
+    Paragraph::new(Text::from(
+        right.iter().copied().flat_map(group).collect::<Vec<_>>(),
+    ))
+    .render(right_area, buf);
+}
+
+fn panel_block(title: &str, hints: Line<'static>, theme: &Theme) -> Block<'static> {
+    Block::bordered()
+        .title(format!(" {} ", title))
+        .title_bottom(hints.centered())
+        .border_style(theme.border)
+        .border_set(border::DOUBLE)
+}
+
+fn render_settings(
+    view: &SettingsView,
+    config: &Config,
+    keymap: &Keymap,
+    theme: &Theme,
+    targets: &mut Targets,
+    area: Rect,
+    buf: &mut Buffer,
+) {
+    let context = if view.editing.is_some() {
+        Context::EditSetting
+    } else {
+        Context::Settings
+    };
+    let hints = footer(context.bindings(), keymap, theme, bottom_row(area), targets);
+    let block = panel_block("Settings", hints, theme);
+    let inner = block.inner(area);
+    block.render(area, buf);
+
+    let [table_area, error_area] = Layout::vertical([
+        Constraint::Length(Setting::ALL.len() as u16),
+        Constraint::Min(0),
+    ])
+    .areas(inner);
+    push_rows(table_area, 0, Setting::ALL.len(), targets);
+
+    let rows: Vec<Row> = Setting::ALL
+        .iter()
+        .enumerate()
+        .map(|(index, setting)| {
+            let value = match &view.editing {
+                Some(text) if index == view.selected => format!("{}▏", text),
+                _ => setting.value(config),
+            };
+            let row = Row::new(vec![format!("  {}", setting.label()), value]);
+            if index == view.selected {
+                row.style(theme.selected)
+            } else {
+                row
+            }
+        })
+        .collect();
+    Table::new(rows, [Constraint::Length(32), Constraint::Min(0)]).render(table_area, buf);
+
+    if let Some(error) = &view.error {
+        Paragraph::new(Line::from(format!(" ⚠ {}", error)).style(theme.error.bold()))
+            .render(error_area, buf);
+    }
+}
+
+fn render_history(
+    view: &HistoryView,
+    keymap: &Keymap,
+    theme: &Theme,
+    targets: &mut Targets,
+    area: Rect,
+    buf: &mut Buffer,
+) {
+    let context = if view.filtering {
+        Context::FilterHistory
+    } else {
+        Context::History
+    };
+    let hints = footer(context.bindings(), keymap, theme, bottom_row(area), targets);
+    let visible = view.visible();
+    let title = format!(
+        "History: {} of {} jobs, by {}",
+        visible.len(),
+        view.entries.len(),
+        view.sort.label()
+    );
+    let block = panel_block(&title, hints, theme);
+    let inner = block.inner(area);
+    block.render(area, buf);
+
+    let [filter_area, table_area] =
+        Layout::vertical([Constraint::Length(1), Constraint::Min(0)]).areas(inner);
+    if view.filtering || !view.filter.is_empty() {
+        let cursor = if view.filtering { "▏" } else { "" };
+        Paragraph::new(Line::from(vec![
+            " Filter: ".set_style(theme.muted),
+            format!("{}{}", view.filter, cursor).set_style(theme.highlight),
+        ]))
+        .render(filter_area, buf);
+    }
+
+    // Keep the selected row on screen: the table has one header row
+    let height = table_area.height.saturating_sub(1).max(1) as usize;
+    let offset = view.selected.saturating_sub(height - 1);
+    let rows_area = Rect {
+        y: table_area.y + 1,
+        height: table_area.height.saturating_sub(1),
+        ..table_area
+    };
+    push_rows(rows_area, offset, visible.len(), targets);
+    let rows: Vec<Row> = visible
+        .iter()
+        .enumerate()
+        .skip(offset)
+        .take(height)
+        .map(|(index, entry)| {
+            let name = entry
+                .input
+                .file_name()
+                .unwrap_or(entry.input.as_os_str())
+                .to_string_lossy()
+                .to_string();
+            let row = Row::new(vec![
+                format!(" {}", history::format_time(entry.finished_at)),
+                entry.kind.label().to_string(),
+                name,
+                entry.level.map_or("-", |l| l.label()).to_string(),
+                human_bytes(entry.input_size),
+                format!("{:.2}%", entry.ratio),
+                history::format_duration(entry.duration_ms),
+                entry.outcome().to_string(),
+            ]);
+            if index == view.selected {
+                row.style(theme.selected)
+            } else if entry.succeeded() {
+                row
+            } else {
+                row.style(theme.error)
+            }
+        })
+        .collect();
+    Table::new(
+        rows,
+        [
+            Constraint::Length(17),
+            Constraint::Length(10),
+            Constraint::Fill(1),
+            Constraint::Length(6),
+            Constraint::Length(10),
+            Constraint::Length(8),
+            Constraint::Length(8),
+            Constraint::Fill(1),
+        ],
+    )
+    .header(
+        Row::new(vec![
+            " Finished",
+            "Job",
+            "File",
+            "Level",
+            "Input",
+            "Ratio",
+            "Time",
+            "Outcome",
+        ])
+        .bold(),
+    )
+    .render(table_area, buf);
+}
+
+fn render_info(
+    info: &FileInfo,
+    keymap: &Keymap,
+    theme: &Theme,
+    targets: &mut Targets,
+    area: Rect,
+    buf: &mut Buffer,
+) {
+    let mut lines: Vec<Line> = info
+        .to_string()
+        .lines()
+        .map(|line| Line::from(format!(" {}", line)))
+        .collect();
+    lines.push(Line::from(""));
+    lines.extend(
+        info.frame_lines()
+            .into_iter()
+            .map(|line| Line::from(line).style(theme.muted)),
+    );
+    Paragraph::new(Text::from(lines))
+        .block(panel_block(
+            "Info",
+            footer(
+                Context::Info.bindings(),
+                keymap,
+                theme,
+                bottom_row(area),
+                targets,
+            ),
+            theme,
+        ))
+        .render(area, buf);
+}
+
+fn render_bench(
+    bench: &Benchmark,
+    current: CompressionLevel,
+    keymap: &Keymap,
+    theme: &Theme,
+    targets: &mut Targets,
+    area: Rect,
+    buf: &mut Buffer,
+) {
+    let title = match bench.sample {
+        Some((sample, file)) if sample < file => format!(
+            "Benchmark: {} (sample of {} from {})",
+            bench.file_name,
+            human_bytes(sample),
+            human_bytes(file)
+        ),
+        _ => format!("Benchmark: {}", bench.file_name),
+    };
+    let block = panel_block(
+        &title,
+        footer(
+            Context::Bench.bindings(),
+            keymap,
+            theme,
+            bottom_row(area),
+            targets,
+        ),
+        theme,
+    );
+    let inner = block.inner(area);
+    block.render(area, buf);
+
+    let [table_area, charts_area] = Layout::vertical([
+        Constraint::Length(CompressionLevel::ALL.len() as u16 + 1),
+        Constraint::Min(0),
+    ])
+    .areas(inner);
+    // Below the header; the row of a level still running can't be picked
+    let results_area = Rect {
+        y: table_area.y + 1,
+        height: table_area.height.saturating_sub(1),
+        ..table_area
+    };
+    push_rows(results_area, 0, bench.results.len(), targets);
+
+    let mut rows: Vec<Row> = bench
+        .results
+        .iter()
+        .enumerate()
+        .map(|(index, result)| {
+            let marker = if result.level == current { "*" } else { " " };
+            let row = Row::new(vec![
+                format!("{} {}", marker, result.level.label()),
+                human_bytes(result.compressed_size),
+                format!("{:.2}%", result.ratio()),
+                format!("{}/s", human_bytes(result.compress_speed() as u64)),
+                format!("{}/s", human_bytes(result.decompress_speed() as u64)),
+            ]);
+            if index == bench.selected {
+                row.style(theme.selected)
+            } else {
+                row
+            }
+        })
+        .collect();
+    if let Some(level) = bench.running {
+        rows.push(
+            Row::new(vec![
+                format!("  {}", level.label()),
+                "running...".to_string(),
+            ])
+            .style(theme.muted),
+        );
+    }
+    Table::new(
+        rows,
+        [
+            Constraint::Length(10),
+            Constraint::Length(12),
+            Constraint::Length(9),
+            Constraint::Length(14),
+            Constraint::Length(14),
+        ],
+    )
+    .header(Row::new(vec!["  Level", "Size", "Ratio", "Compress", "Decompress"]).bold())
+    .render(table_area, buf);
+
+    // One horizontal bar chart per metric; each scales to its own maximum
+    let speed = |v: f64| format!("{}/s", human_bytes(v as u64));
+    let [ratio_area, compress_area, decompress_area] =
+        Layout::horizontal([Constraint::Ratio(1, 3); 3]).areas(charts_area);
+    metric_chart(
+        "Ratio",
+        &bench.results,
+        LevelResult::ratio,
+        |v| format!("{:.1}%", v),
+        theme,
+    )
+    .render(ratio_area, buf);
+    metric_chart(
+        "Compress",
+        &bench.results,
+        LevelResult::compress_speed,
+        speed,
+        theme,
+    )
+    .render(compress_area, buf);
+    metric_chart(
+        "Decompress",
+        &bench.results,
+        LevelResult::decompress_speed,
+        speed,
+        theme,
+    )
+    .render(decompress_area, buf);
+}
+
+fn metric_chart<'a>(
+    title: &str,
+    results: &[LevelResult],
+    metric: impl Fn(&LevelResult) -> f64,
+    label: impl Fn(f64) -> String,
+    theme: &Theme,
+) -> BarChart<'a> {
+    let bars: Vec<Bar> = results
+        .iter()
+        .map(|result| {
+            let value = metric(result);
+            Bar::default()
+                // Ratios are small percentages; keep a decimal of precision in the bar length
+                .value((value * 10.0) as u64)
+                .label(Line::from(result.level.label()))
+                .text_value(label(value))
+        })
+        .collect();
+    BarChart::horizontal(bars)
+        .block(
+            Block::bordered()
+                .title(format!(" {} ", title))
+                .border_style(theme.border),
+        )
+        .bar_width(1)
+        .bar_gap(1)
+        .bar_style(theme.highlight)
+}
+
+// What is running or waiting for Enter, or else how the last job went.
+fn render_job(app: &App, area: Rect, buf: &mut Buffer) {
+    let theme = &app.theme;
+    let field = |name: &str, value: String| {
+        Line::from(vec![
+            format!(" {:<9}", name).set_style(theme.muted),
+            value.into(),
+        ])
+    };
+    let path = |path: &Option<PathBuf>| {
+        path.as_ref()
+            .map_or("-".to_string(), |p| p.display().to_string())
+    };
+    let level = |level: Option<CompressionLevel>| level.map_or("-", |l| l.label()).to_string();
+
+    let (title, lines) = if let Some(job) = &app.running_job {
+        let elapsed = job.started.elapsed().as_millis() as u64;
+        (
+            "Running",
+            vec![
+                field("Job", job.kind.label().to_string()),
+                field("Input", job.input.display().to_string()),
+                field("Output", path(&job.output)),
+                field("Level", level(job.level)),
+                field(
+                    "Progress",
+                    format!(
+                        "{:.0}% after {}",
+                        app.progress * 100.0,
+                        history::format_duration(elapsed)
+                    ),
+                ),
+            ],
+        )
+    } else if let Some(pending) = &app.pending {
+        let output = match app.pending_output() {
+            Some(Ok(path)) => path.display().to_string(),
+            Some(Err(e)) => e,
+            None => "-".to_string(),
+        };
+        (
+            "Waiting for Enter",
+            vec![
+                field("Input", pending.input_path.display().to_string()),
+                field("Output", output),
+                field("Level", app.compression_level.label().to_string()),
+            ],
+        )
+    } else if let Some(result) = &app.last_result {
+        let entry = &result.entry;
+        let mut lines = vec![
+            field("Job", entry.kind.label().to_string()),
+            field("Input", entry.input.display().to_string()),
+            field("Output", path(&entry.output)),
+            field("Outcome", entry.outcome().to_string()),
+        ];
+        if entry.succeeded() {
+            lines.push(field(
+                "Size",
+                format!(
+                    "{} -> {} ({:.2}%) in {}",
+                    human_bytes(entry.input_size),
+                    human_bytes(entry.output_size),
+                    entry.ratio,
+                    history::format_duration(entry.duration_ms)
+                ),
+            ));
+        }
+        ("Last job", lines)
+    } else {
+        ("Job", vec![Line::from(" Nothing yet").style(theme.muted)])
+    };
+    Paragraph::new(Text::from(lines))
+        .block(side_block(title, theme))
+        .render(area, buf);
+}
+
+// The newest jobs from the history file, newest first.
+fn render_recent(recent: Option<&[HistoryEntry]>, theme: &Theme, area: Rect, buf: &mut Buffer) {
+    let block = side_block("Recent jobs", theme);
+    let inner = block.inner(area);
+    block.render(area, buf);
+    let Some(recent) = recent else {
+        Paragraph::new(Line::from(" Loading ...").style(theme.muted)).render(inner, buf);
+        return;
+    };
+    let rows: Vec<Row> = recent
+        .iter()
+        .rev()
+        .take(inner.height as usize)
+        .map(|entry| {
+            let name = entry
+                .input
+                .file_name()
+                .unwrap_or(entry.input.as_os_str())
+                .to_string_lossy()
+                .to_string();
+            let row = Row::new(vec![
+                format!(" {}", history::format_time(entry.finished_at)),
+                name,
+                format!("{:.2}%", entry.ratio),
+                entry.outcome().to_string(),
+            ]);
+            if entry.succeeded() {
+                row
+            } else {
+                row.style(theme.error)
+            }
+        })
+        .collect();
+    Table::new(
+        rows,
+        [
+            Constraint::Length(17),
+            Constraint::Fill(2),
+            Constraint::Length(8),
+            Constraint::Fill(1),
+        ],
+    )
+    .render(inner, buf);
+}
+
+// Every status message so far, the newest at the bottom.
+fn render_log(log: &[(u64, String)], theme: &Theme, area: Rect, buf: &mut Buffer) {
+    let block = side_block("Log", theme);
+    let inner = block.inner(area);
+    block.render(area, buf);
+    let skip = log.len().saturating_sub(inner.height as usize);
+    let lines: Vec<Line> = log[skip..]
+        .iter()
+        .map(|(secs, message)| {
+            let seconds = secs % 86_400;
+            Line::from(vec![
+                format!(
+                    " {:02}:{:02}:{:02} ",
+                    seconds / 3600,
+                    seconds % 3600 / 60,
+                    seconds % 60
+                )
+                .set_style(theme.muted),
+                message.trim().to_string().into(),
+            ])
+        })
+        .collect();
+    Paragraph::new(Text::from(lines)).render(inner, buf);
+}
+
+fn side_block(title: &str, theme: &Theme) -> Block<'static> {
+    Block::bordered()
+        .title(format!(" {} ", title))
+        .border_style(theme.border)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::PendingJob;
+    use crate::history::{JobKind, JobStart};
+    use crate::report::JobResult;
+    use ratatui::{Terminal, backend::TestBackend};
+    use std::time::Duration;
+
+    // The screen as text, the way a terminal of this size would show it.
+    fn render(app: &mut App, width: u16, height: u16) -> TestBackend {
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+        terminal
+            .draw(|frame| frame.render_widget(&mut *app, frame.area()))
+            .unwrap();
+        terminal.backend().clone()
+    }
+
+    fn entry(name: &str, ratio: f64, error: Option<&str>) -> HistoryEntry {
+        HistoryEntry {
+            finished_at: 1_767_225_600, // 2026-01-01 00:00 UTC
+            kind: JobKind::Compress,
+            input: PathBuf::from("/data").join(name),
+            output: Some(PathBuf::from("/data").join(format!("{}.zst", name))),
+            input_size: 4 << 20,
+            output_size: ((4 << 20) as f64 * ratio / 100.0) as u64,
+            ratio,
+            duration_ms: 1_250,
+            level: Some(CompressionLevel::Normal),
+            error: error.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn main_screen_states() {
+        let mut app = App::default();
+        insta::assert_snapshot!("idle", render(&mut app, 100, 20));
+
+        for level in [CompressionLevel::Fast, CompressionLevel::Best] {
+            app.compression_level = level;
+            insta::assert_snapshot!(
+                format!("level_{}", level.label().to_lowercase()),
+                render(&mut app, 100, 20)
+            );
+        }
+        app.compression_level = CompressionLevel::Normal;
+        app.job_options.remove_source = true;
+        insta::assert_snapshot!("remove_source_on", render(&mut app, 100, 20));
+        app.job_options.remove_source = false;
+
+        app.pending = Some(PendingJob {
+            input_path: PathBuf::from("/data/report.pdf"),
+            estimate: None,
+        });
+        app.status_message =
+            " \"report.pdf\": no estimate (sample too small) | Enter to compress, Esc to cancel"
+                .to_string();
+        insta::assert_snapshot!("pending", render(&mut app, 100, 20));
+        app.pending = None;
+
+        app.is_compressing = true;
+        app.status_message = " Compressing \"report.pdf\"".to_string();
+        for progress in [0.0, 0.37, 0.995] {
+            app.progress = progress;
+            insta::assert_snapshot!(
+                format!("compressing_{}", (progress * 100.0) as u16),
+                render(&mut app, 100, 20)
+            );
+        }
+
+        app.is_compressing = false;
+        app.progress = 1.0;
+        app.status_message = " Compression complete!".to_string();
+        insta::assert_snapshot!("finished", render(&mut app, 100, 20));
+
+        app.progress = 0.0;
+        app.status_message = " Error: /data/report.pdf: permission denied".to_string();
+        insta::assert_snapshot!("error", render(&mut app, 100, 20));
+    }
+
+    #[test]
+    fn panels_and_overlays() {
+        let mut app = App::default();
+        app.handle_action(Action::Settings);
+        insta::assert_snapshot!("settings", render(&mut app, 100, 32));
+        if let Some(Panel::Settings(view)) = &mut app.panel {
+            view.selected = 6;
+            view.editing = Some("{stem}-{date}".to_string());
+            view.error = Some("the template must end in .zst".to_string());
+        }
+        insta::assert_snapshot!("settings_editing", render(&mut app, 100, 32));
+
+        let mut view = HistoryView::new(vec![
+            entry("a.log", 12.5, None),
+            entry("b.iso", 98.1, None),
+            entry("c.db", 0.0, Some("permission denied")),
+        ]);
+        view.selected = 1;
+        app.status_message.clear();
+        app.panel = Some(Panel::History(view));
+        insta::assert_snapshot!("history", render(&mut app, 110, 24));
+
+        let result = |level, compressed_size| LevelResult {
+            level,
+            input_size: 1 << 20,
+            compressed_size,
+            compress_time: Duration::from_millis(10),
+            decompress_time: Duration::from_millis(2),
+        };
+        app.panel = Some(Panel::Bench(Benchmark {
+            file_name: "report.pdf".to_string(),
+            results: vec![
+                result(CompressionLevel::Fast, 400 << 10),
+                result(CompressionLevel::Normal, 350 << 10),
+            ],
+            selected: 1,
+            running: Some(CompressionLevel::Best),
+            sample: Some((1 << 20, 8 << 20)),
+        }));
+        insta::assert_snapshot!("bench", render(&mut app, 100, 30));
+
+        let dir = std::env::temp_dir().join("freya_test_ui_info");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("archive.zst");
+        std::fs::write(&path, zstd::encode_all(&[7u8; 10_000][..], 3).unwrap()).unwrap();
+        let mut info = FileInfo::read(&path).unwrap();
+        info.path = PathBuf::from("/data/archive.zst");
+        std::fs::remove_dir_all(&dir).ok();
+        app.panel = Some(Panel::Info(info));
+        insta::assert_snapshot!("info", render(&mut app, 100, 30));
+
+        app.panel = None;
+        app.handle_action(Action::Help);
+        insta::assert_snapshot!("help", render(&mut app, 120, 50));
+    }
+
+    #[test]
+    fn compact_and_dashboard_layouts() {
+        let mut app = App::default();
+        insta::assert_snapshot!("compact_idle", render(&mut app, 50, 12));
+        app.is_compressing = true;
+        app.progress = 0.5;
+        app.status_message = " Compressing \"report.pdf\"".to_string();
+        insta::assert_snapshot!("compact_compressing", render(&mut app, 50, 12));
+
+        let mut app = App::default();
+        app.status_message = " Compression complete!".to_string();
+        app.progress = 1.0;
+        let job = JobStart::new(
+            JobKind::Compress,
+            PathBuf::from("/data/a.log"),
+            Some(PathBuf::from("/data/a.log.zst")),
+            Some(CompressionLevel::Normal),
+        );
+        let mut result = JobResult::finished(
+            &job,
+            4 << 20,
+            512 << 10,
+            PathBuf::from("/data/a.log.zst"),
+            false,
+        );
+        result.entry.duration_ms = 1_250;
+        result.entry.finished_at = 1_767_225_662;
+        app.last_result = Some(result);
+        app.recent = Some(vec![
+            entry("a.log", 12.5, None),
+            entry("c.db", 0.0, Some("permission denied")),
+        ]);
+        app.log = vec![
+            (
+                1_767_225_600,
+                " Press 'o' to compress or 'd' to decompress a file".to_string(),
+            ),
+            (1_767_225_660, " Compressing \"a.log\"".to_string()),
+            (1_767_225_662, " Compression complete!".to_string()),
+        ];
+        insta::assert_snapshot!("dashboard", render(&mut app, 160, 40));
+    }
+
+    #[test]
+    fn layout_follows_the_terminal_size_and_footers_fit() {
+        let mode = |width, height| LayoutMode::for_area(Rect::new(0, 0, width, height));
+        assert_eq!(mode(50, 30), LayoutMode::Compact);
+        assert_eq!(mode(120, 12), LayoutMode::Compact);
+        assert_eq!(mode(100, 30), LayoutMode::Standard);
+        assert_eq!(mode(200, 30), LayoutMode::Standard);
+        assert_eq!(mode(200, 50), LayoutMode::Dashboard);
+
+        // Hints that don't fit go, Help and Quit stay, and the rest stays clickable
+        let keymap = Keymap::default();
+        let mut targets = Targets::default();
+        let row = Rect::new(0, 0, 40, 1);
+        let line = footer(
+            Context::Main.hints(),
+            &keymap,
+            &Theme::DARK,
+            row,
+            &mut targets,
+        );
+        let text = line.to_string();
+        assert!(line.width() <= 40, "{}", text);
+        assert!(
+            text.contains("Help <?>") && text.contains("Quit <q>"),
+            "{}",
+            text
+        );
+        assert!(!text.contains("Settings"), "{}", text);
+        let before_quit = text[..text.find("Quit").unwrap()].chars().count() as u16;
+        let quit = before_quit + (40 - line.width() as u16) / 2;
+        assert_eq!(targets.at(quit, 0), Some(Target::Action(Action::Quit)));
     }
 }